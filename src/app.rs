@@ -1,13 +1,40 @@
-use std::io::Cursor;
+use std::{collections::HashMap, path::Path};
 
 use crate::riders::{
-    gvr_texture::GVRTexture,
+    gvr_texture::{
+        constraints::GVRConstraintViolation,
+        decode::GVRTexel,
+        diff::{diff_textures, GVRTextureDiff, GVRTextureMatch, GVRVisualDiff},
+        dolphin::{DolphinPackReport, DolphinTexturePack},
+        encode::{CMPRQuality, GVREncodeOptions},
+        image::RGBAImage,
+        mipmap::{generate_mip_levels, GVRMipmapFilter},
+        GVRDataFormat, GVRPaletteFormat, GVRTexture, GVRVariant, GVR_FLAG_EXTERNAL_PALETTE,
+        GVR_FLAG_INTERNAL_PALETTE, GVR_FLAG_MIPMAPS,
+    },
     packman_archive::{PackManArchive, PackManFile, PackManFolder},
-    texture_archive::TextureArchive,
+    texture_archive::{
+        TextureArchive, TextureArchiveEntry, TextureArchiveError, TextureArchiveReadMode,
+        ARCHIVE_FLAG_NAMES,
+    },
 };
 use egui::Color32;
 use egui_modal::{Icon, Modal};
 use strum::IntoEnumIterator;
+use xxhash_rust::xxh64::xxh64;
+
+/// The size of the texture thumbnails in the texture list, in points.
+const THUMBNAIL_SIZE: f32 = 48.0;
+/// The maximum width and height of the texture thumbnails in pixels. Bigger textures are
+/// downscaled to fit.
+const THUMBNAIL_MAX_PIXELS: u32 = 64;
+/// The size of a single square of the checkerboard shown behind textures in the texture viewer,
+/// in points.
+const CHECKER_SIZE: f32 = 8.0;
+/// The zoom range of the texture viewer.
+const VIEWER_ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.125..=64.0;
+/// The maximum width and height of the previews in the format conversion window, in points.
+const CONVERSION_PREVIEW_SIZE: f32 = 256.0;
 
 #[derive(PartialEq, Clone, Default, strum::Display, strum::EnumIter)]
 enum AppTabs {
@@ -32,6 +59,388 @@ struct GraphicalArchiveContext {
 struct TextureArchiveContext {
     picked_file: Option<String>,
     archive: Option<TextureArchive>,
+    /// The GVR format that newly added PNG, DDS and TGA images are converted into.
+    image_format: GVRDataFormat,
+    /// The thumbnails of the textures in the texture list.
+    thumbnails: ThumbnailCache,
+    /// The viewer of the texture that's selected in the texture list.
+    viewer: TextureViewer,
+    /// The last comparison between two texture archives or two textures, if it's still open.
+    comparison: Option<TextureComparison>,
+    /// The format conversion of a texture in the texture list that's being previewed, if any.
+    conversion: Option<FormatConversion>,
+    /// The GX constraints broken by the textures in the texture list, keyed by the hash of the
+    /// texture data like [`ThumbnailCache`].
+    violations: HashMap<u64, Vec<GVRConstraintViolation>>,
+    /// Whether archives are opened in [`TextureArchiveReadMode::Lenient`] mode, keeping damaged
+    /// entries instead of failing to open.
+    salvage: bool,
+}
+
+/// A conversion of a texture in the texture list into another format, which is previewed in its
+/// own window before it's applied.
+struct FormatConversion {
+    /// The index of the converted texture in the texture list.
+    index: usize,
+    /// The hash of the texture data at the start of the conversion. The conversion is dropped if
+    /// the texture at [`FormatConversion::index`] no longer matches it.
+    source_key: u64,
+    /// The options the texture is converted with.
+    options: GVREncodeOptions,
+    /// The decoded base texture of the original texture.
+    original: Result<egui::TextureHandle, String>,
+    /// The options of the last conversion, along with the converted texture and its decoded base
+    /// texture.
+    converted: Option<(GVREncodeOptions, ConversionResult)>,
+}
+
+/// The converted texture of a [`FormatConversion`] and its decoded base texture, or the reason
+/// the texture couldn't be converted.
+type ConversionResult = Result<(GVRTexture, egui::TextureHandle), String>;
+
+impl FormatConversion {
+    /// Starts converting `tex`, the texture at `index` of the texture list. The options match
+    /// `tex` at first, falling back to the default format if its own format can't be encoded.
+    fn new(ctx: &egui::Context, tex: &GVRTexture, index: usize) -> Self {
+        let mut options = GVREncodeOptions::matching(tex);
+        if !options.format.is_encodable() {
+            options.format = GVRDataFormat::default();
+        }
+
+        let original = tex
+            .decode()
+            .map(|image| load_rgba_image(ctx, "conversion-original", &image))
+            .map_err(|err| err.to_string());
+
+        Self {
+            index,
+            source_key: ThumbnailCache::key(tex),
+            options,
+            original,
+            converted: None,
+        }
+    }
+
+    /// Converts `tex` with the current options, unless it was already converted with them.
+    fn refresh(&mut self, ctx: &egui::Context, tex: &GVRTexture) {
+        if self
+            .converted
+            .as_ref()
+            .is_some_and(|(options, _)| *options == self.options)
+        {
+            return;
+        }
+
+        let converted = tex
+            .convert(&self.options)
+            .map_err(|err| err.to_string())
+            .and_then(|converted| {
+                let image = converted.decode().map_err(|err| err.to_string())?;
+                let handle = load_rgba_image(ctx, "conversion-converted", &image);
+                Ok((converted, handle))
+            });
+        self.converted = Some((self.options.clone(), converted));
+    }
+}
+
+/// Loads `image` into a new egui texture called `name`, sampled with nearest filtering so that
+/// single pixels stay sharp when zoomed in.
+fn load_rgba_image(ctx: &egui::Context, name: &str, image: &RGBAImage) -> egui::TextureHandle {
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(
+        [image.width as usize, image.height as usize],
+        &image.pixels,
+    );
+    ctx.load_texture(name, color_image, egui::TextureOptions::NEAREST)
+}
+
+/// The result of comparing two texture archives or two textures, shown in its own window.
+struct TextureComparison {
+    /// Describes what was compared.
+    title: String,
+    rows: Vec<ComparisonRow>,
+    /// The row whose heatmap is shown, along with the heatmap as an egui texture.
+    heatmap: Option<(usize, egui::TextureHandle)>,
+}
+
+/// A single matched, removed or added texture in a [`TextureComparison`].
+struct ComparisonRow {
+    /// The index and name of the texture in the old texture list, if it's there.
+    old: Option<(usize, String)>,
+    /// The index and name of the texture in the new texture list, if it's there.
+    new: Option<(usize, String)>,
+    /// The differences between the textures, if the texture is in both lists.
+    diff: Option<GVRTextureDiff>,
+}
+
+impl TextureComparison {
+    /// Compares the `old` textures with the `new` textures. See [`diff_textures()`] for more
+    /// details.
+    fn new(title: String, old: &[&GVRTexture], new: &[&GVRTexture]) -> Self {
+        let entry = |textures: &[&GVRTexture], i: usize| Some((i, textures[i].name.clone()));
+
+        let rows = diff_textures(old, new)
+            .into_iter()
+            .map(|texture_match| match texture_match {
+                GVRTextureMatch::Matched {
+                    old: i,
+                    new: j,
+                    diff,
+                } => ComparisonRow {
+                    old: entry(old, i),
+                    new: entry(new, j),
+                    diff: Some(diff),
+                },
+                GVRTextureMatch::Removed(i) => ComparisonRow {
+                    old: entry(old, i),
+                    new: None,
+                    diff: None,
+                },
+                GVRTextureMatch::Added(j) => ComparisonRow {
+                    old: None,
+                    new: entry(new, j),
+                    diff: None,
+                },
+            })
+            .collect();
+
+        Self {
+            title,
+            rows,
+            heatmap: None,
+        }
+    }
+
+    /// Summarizes the outcome of the comparison in a single line.
+    fn summary(&self) -> String {
+        let count = |outcome: &str| {
+            self.rows
+                .iter()
+                .filter(|row| row.outcome() == outcome)
+                .count()
+        };
+
+        format!(
+            "{} identical, {} with the same pixels, {} changed, {} removed, {} added",
+            count("Identical"),
+            count("Same pixels"),
+            self.rows.len()
+                - count("Identical")
+                - count("Same pixels")
+                - count("Removed")
+                - count("Added"),
+            count("Removed"),
+            count("Added"),
+        )
+    }
+}
+
+impl ComparisonRow {
+    /// Describes how the textures of this row differ.
+    fn outcome(&self) -> &'static str {
+        let Some(diff) = &self.diff else {
+            return if self.old.is_some() {
+                "Removed"
+            } else {
+                "Added"
+            };
+        };
+
+        if diff.bytes_identical {
+            "Identical"
+        } else if diff.is_visually_identical() {
+            "Same pixels"
+        } else {
+            match diff.visual {
+                GVRVisualDiff::Compared(_) => "Changed",
+                GVRVisualDiff::DimensionsDiffer => "Resized",
+                GVRVisualDiff::Undecodable(_) => "Undecodable",
+            }
+        }
+    }
+}
+
+/// The state of the texture viewer, which shows a single texture of the texture list up close.
+struct TextureViewer {
+    /// The index of the shown texture in the texture list, if any.
+    selected: Option<usize>,
+    /// The shown mip level, where 0 is the base texture.
+    mip_level: usize,
+    /// Which of the R, G, B and A channels are shown.
+    channels: [bool; 4],
+    /// The amount of screen points a single pixel of the texture takes up.
+    zoom: f32,
+    /// The offset of the texture center from the center of the view, in points.
+    pan: egui::Vec2,
+    /// The decoded mip level that's currently shown, keyed by the texture data hash, the mip
+    /// level and the shown channels.
+    image: Option<(ViewerImageKey, Result<ViewerImage, String>)>,
+}
+
+/// The texture data hash, mip level and shown channels of a [`ViewerImage`].
+type ViewerImageKey = (u64, usize, [bool; 4]);
+
+/// A decoded mip level in the texture viewer.
+struct ViewerImage {
+    /// The decoded pixels, with every channel intact.
+    decoded: RGBAImage,
+    /// The egui texture of the pixels, with the hidden channels masked out.
+    handle: egui::TextureHandle,
+}
+
+impl Default for TextureViewer {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            mip_level: 0,
+            channels: [true; 4],
+            zoom: 1.0,
+            pan: egui::Vec2::ZERO,
+            image: None,
+        }
+    }
+}
+
+impl TextureViewer {
+    /// Shows the texture at `index` of the texture list, with the view reset.
+    fn select(&mut self, index: usize) {
+        *self = Self {
+            selected: Some(index),
+            channels: self.channels,
+            ..Self::default()
+        };
+    }
+
+    /// Keeps the selection on the same texture when the textures at `a` and `b` are swapped.
+    fn follow_swap(&mut self, a: usize, b: usize) {
+        if self.selected == Some(a) {
+            self.selected = Some(b);
+        } else if self.selected == Some(b) {
+            self.selected = Some(a);
+        }
+    }
+
+    /// Keeps the selection on the same texture when the texture at `index` is inserted or removed.
+    fn follow_shift(&mut self, index: usize, inserted: bool) {
+        self.selected = match self.selected {
+            Some(selected) if selected == index && !inserted => None,
+            Some(selected) if selected >= index && inserted => Some(selected + 1),
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+    }
+
+    /// Decodes the shown mip level of `tex` into [`TextureViewer::image`], unless it's already
+    /// there.
+    fn refresh_image(&mut self, ctx: &egui::Context, tex: &GVRTexture) {
+        let key = (ThumbnailCache::key(tex), self.mip_level, self.channels);
+        if self.image.as_ref().is_none_or(|(cached, _)| *cached != key) {
+            let image = Self::load(ctx, tex, self.mip_level, self.channels);
+            self.image = Some((key, image));
+        }
+    }
+
+    fn load(
+        ctx: &egui::Context,
+        tex: &GVRTexture,
+        level: usize,
+        channels: [bool; 4],
+    ) -> Result<ViewerImage, String> {
+        let decoded = tex.decode_mip_level(level).map_err(|err| err.to_string())?;
+
+        let masked: Vec<u8> = decoded
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|p| Self::mask_channels([p[0], p[1], p[2], p[3]], channels))
+            .collect();
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(
+            [decoded.width as usize, decoded.height as usize],
+            &masked,
+        );
+        let handle = ctx.load_texture("texture-viewer", color_image, egui::TextureOptions::NEAREST);
+
+        Ok(ViewerImage { decoded, handle })
+    }
+
+    /// Hides the channels of `pixel` that aren't enabled in `channels`. Hidden color channels
+    /// become 0 and a hidden alpha channel becomes opaque. If alpha is the only shown channel,
+    /// it's shown in grayscale.
+    fn mask_channels(pixel: [u8; 4], channels: [bool; 4]) -> [u8; 4] {
+        let [r, g, b, a] = pixel;
+        if channels == [false, false, false, true] {
+            return [a, a, a, 0xFF];
+        }
+
+        let mask = |value: u8, shown: bool| if shown { value } else { 0 };
+        [
+            mask(r, channels[0]),
+            mask(g, channels[1]),
+            mask(b, channels[2]),
+            if channels[3] { a } else { 0xFF },
+        ]
+    }
+}
+
+/// Caches the decoded thumbnails of textures as egui textures.
+///
+/// Thumbnails are keyed by a hash of the texture data, so they're reused for identical textures
+/// and no longer used once a texture is replaced. Textures that can't be decoded keep the error.
+#[derive(Default)]
+struct ThumbnailCache {
+    thumbnails: HashMap<u64, Result<egui::TextureHandle, String>>,
+}
+
+impl ThumbnailCache {
+    /// Returns the thumbnail of `tex`, decoding it first if it isn't cached yet.
+    fn get(
+        &mut self,
+        ctx: &egui::Context,
+        tex: &GVRTexture,
+    ) -> &Result<egui::TextureHandle, String> {
+        self.thumbnails
+            .entry(Self::key(tex))
+            .or_insert_with(|| Self::load(ctx, tex))
+    }
+
+    /// Removes the cached thumbnail of `tex`, if there is one.
+    fn invalidate(&mut self, tex: &GVRTexture) {
+        self.thumbnails.remove(&Self::key(tex));
+    }
+
+    /// Removes all the cached thumbnails.
+    fn clear(&mut self) {
+        self.thumbnails.clear();
+    }
+
+    /// Returns the key of `tex` in the cache, the hash of its data.
+    fn key(tex: &GVRTexture) -> u64 {
+        xxh64(tex.data.get_ref(), 0)
+    }
+
+    fn load(ctx: &egui::Context, tex: &GVRTexture) -> Result<egui::TextureHandle, String> {
+        let image = tex.decode().map_err(|err| err.to_string())?;
+
+        // Halve the image until it fits into the thumbnail
+        let max_dimension = image.width.max(image.height);
+        let level_count = max_dimension
+            .div_ceil(THUMBNAIL_MAX_PIXELS)
+            .next_power_of_two()
+            .ilog2() as usize
+            + 1;
+        let thumbnail = generate_mip_levels(&image, level_count, GVRMipmapFilter::Box, false)
+            .pop()
+            .unwrap();
+
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(
+            [thumbnail.width as usize, thumbnail.height as usize],
+            &thumbnail.pixels,
+        );
+
+        Ok(ctx.load_texture(
+            format!("thumbnail-{}", tex.name),
+            color_image,
+            egui::TextureOptions::LINEAR,
+        ))
+    }
 }
 
 #[derive(Default)]
@@ -80,6 +489,579 @@ impl EguiApp {
                 ui.small("No objects.");
             });
         }
+
+        if self.current_tab == AppTabs::TextureArchives {
+            let viewer = &mut self.texture_archive_ctx.viewer;
+            let selected = self
+                .texture_archive_ctx
+                .archive
+                .as_mut()
+                .zip(viewer.selected)
+                .and_then(|(archive, i)| {
+                    let has_flags = archive.is_without_model;
+                    let entry = archive.entries.get_mut(i)?;
+                    Some((&mut entry.texture, has_flags.then_some(&mut entry.flags)))
+                });
+
+            match selected {
+                Some((tex, flags)) => {
+                    egui::SidePanel::right("texture-viewer")
+                        .default_width(400.0)
+                        .show(ctx, |ui| {
+                            Self::draw_texture_viewer(ui, viewer, tex, flags);
+                        });
+                }
+                None => viewer.selected = None,
+            }
+        }
+    }
+
+    /// Draws the texture viewer for `tex`: its header, the view controls, the texture itself and
+    /// the pixel under the cursor. The archive `flags` of the texture can be edited as well, if
+    /// the archive has them.
+    fn draw_texture_viewer(
+        ui: &mut egui::Ui,
+        viewer: &mut TextureViewer,
+        tex: &mut GVRTexture,
+        flags: Option<&mut u8>,
+    ) {
+        ui.horizontal(|ui| {
+            ui.heading(&tex.name);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("Close").clicked() {
+                    viewer.selected = None;
+                }
+            });
+        });
+
+        let header = &tex.header;
+        let mip_levels = tex.mip_levels();
+
+        egui::CollapsingHeader::new("Header")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("texture-viewer-header")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut row = |name: &str, value: String| {
+                            ui.label(name);
+                            ui.monospace(value);
+                            ui.end_row();
+                        };
+
+                        row("Format", header.data_format.to_string());
+                        row("Dimensions", format!("{}x{}", header.width, header.height));
+                        if header.data_format.is_palettized() {
+                            row("Palette format", header.palette_format.to_string());
+                        }
+                        row("Flags", Self::describe_flags(header.flags));
+                        row("Global index", header.global_index.to_string());
+                        row(
+                            "Mip levels",
+                            format!("{} of {}", mip_levels.len(), header.mip_level_count()),
+                        );
+                        row("Size", format!("{} bytes", tex.size));
+                        row("Read as", tex.source_variant.to_string());
+                    });
+            });
+
+        if let Some(flags) = flags {
+            egui::CollapsingHeader::new("Archive flags")
+                .default_open(true)
+                .show(ui, |ui| {
+                    Self::draw_archive_flags(ui, flags);
+                });
+        }
+
+        ui.horizontal(|ui| {
+            viewer.mip_level = viewer.mip_level.min(mip_levels.len().saturating_sub(1));
+            egui::ComboBox::from_id_salt("texture-viewer-mip-level")
+                .selected_text(format!("Mip level {}", viewer.mip_level))
+                .show_ui(ui, |ui| {
+                    for level in &mip_levels {
+                        ui.selectable_value(
+                            &mut viewer.mip_level,
+                            level.level,
+                            format!("{}: {}x{}", level.level, level.width, level.height),
+                        );
+                    }
+                });
+
+            for (shown, name) in viewer.channels.iter_mut().zip(["R", "G", "B", "A"]) {
+                ui.toggle_value(shown, name);
+            }
+
+            if ui
+                .button("Reset view")
+                .on_hover_text("Resets the zoom and centers the texture.")
+                .clicked()
+            {
+                viewer.zoom = 1.0;
+                viewer.pan = egui::Vec2::ZERO;
+            }
+        });
+
+        // Leave room for the pixel readout below the view
+        let readout_height = ui.text_style_height(&egui::TextStyle::Monospace) * 2.0
+            + ui.spacing().item_spacing.y * 2.0;
+        let view_size = egui::vec2(
+            ui.available_width(),
+            (ui.available_height() - readout_height).max(100.0),
+        );
+        let (rect, response) = ui.allocate_exact_size(view_size, egui::Sense::drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        viewer.refresh_image(ui.ctx(), tex);
+        let image = match &viewer.image {
+            Some((_, Ok(image))) => image,
+            Some((_, Err(err))) => {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    format!("No preview: {err}"),
+                    egui::FontId::proportional(14.0),
+                    ui.visuals().weak_text_color(),
+                );
+                return;
+            }
+            None => return,
+        };
+        let image_size = egui::vec2(image.decoded.width as f32, image.decoded.height as f32);
+
+        // Zoom around the cursor, so the pixel under it stays in place
+        if let Some(pos) = response.hover_pos() {
+            let factor = ui.input(|i| i.zoom_delta() * (i.smooth_scroll_delta.y / 200.0).exp());
+            let zoom =
+                (viewer.zoom * factor).clamp(*VIEWER_ZOOM_RANGE.start(), *VIEWER_ZOOM_RANGE.end());
+            if zoom != viewer.zoom {
+                let center = rect.center() + viewer.pan;
+                viewer.pan = pos + (center - pos) * (zoom / viewer.zoom) - rect.center();
+                viewer.zoom = zoom;
+            }
+        }
+        viewer.pan += response.drag_delta();
+
+        let image_rect =
+            egui::Rect::from_center_size(rect.center() + viewer.pan, image_size * viewer.zoom);
+        Self::draw_checkerboard(&painter, image_rect.intersect(rect), ui.visuals().dark_mode);
+
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        painter.image(image.handle.id(), image_rect, uv, Color32::WHITE);
+
+        let hovered_pixel = response
+            .hover_pos()
+            .map(|pos| ((pos - image_rect.min) / viewer.zoom).floor())
+            .filter(|pixel| {
+                (0.0..image_size.x).contains(&pixel.x) && (0.0..image_size.y).contains(&pixel.y)
+            });
+
+        match hovered_pixel {
+            Some(pixel) => {
+                let (x, y) = (pixel.x as u32, pixel.y as u32);
+                let [r, g, b, a] = image.decoded.get_pixel(x, y);
+                let texel = tex
+                    .texel(viewer.mip_level, x, y)
+                    .map(|texel| Self::describe_texel(texel, header.data_format))
+                    .unwrap_or_else(|| "-".to_string());
+
+                ui.monospace(format!(
+                    "({x}, {y})  RGBA {r} {g} {b} {a}  #{r:02X}{g:02X}{b:02X}{a:02X}"
+                ));
+                ui.monospace(format!("Raw texel: {texel}"));
+            }
+            None => {
+                ui.monospace(format!("Zoom: {}%", (viewer.zoom * 100.0).round()));
+            }
+        }
+    }
+
+    /// Draws the window previewing the format `conversion` of the texture of one of the
+    /// `entries`, if there is one. Applying the conversion replaces the texture, while closing the
+    /// window discards it.
+    fn draw_format_conversion(
+        ctx: &egui::Context,
+        conversion: &mut Option<FormatConversion>,
+        entries: &mut [TextureArchiveEntry],
+        thumbnails: &mut ThumbnailCache,
+    ) {
+        let Some(current) = conversion else {
+            return;
+        };
+        let Some(tex) = entries
+            .get(current.index)
+            .map(|entry| &entry.texture)
+            .filter(|tex| ThumbnailCache::key(tex) == current.source_key)
+        else {
+            // The texture was removed or replaced in the meantime
+            *conversion = None;
+            return;
+        };
+
+        let mut open = true;
+        let mut applied = false;
+        egui::Window::new(format!("Convert format of \"{}\"", tex.name))
+            .id(egui::Id::new("texture-format-conversion"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let options = &mut current.options;
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("conversion-format")
+                        .selected_text(format!("Format: {}", options.format))
+                        .show_ui(ui, |ui| {
+                            for f in GVRDataFormat::iter().filter(|f| f.is_encodable()) {
+                                ui.selectable_value(&mut options.format, f, f.to_string());
+                            }
+                        });
+
+                    if options.format.is_palettized() {
+                        egui::ComboBox::from_id_salt("conversion-palette-format")
+                            .selected_text(format!("Palette: {}", options.palette_format))
+                            .show_ui(ui, |ui| {
+                                for f in GVRPaletteFormat::iter() {
+                                    ui.selectable_value(
+                                        &mut options.palette_format,
+                                        f,
+                                        f.to_string(),
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut options.dither, "Dither");
+                    }
+
+                    if options.format == GVRDataFormat::CMPR {
+                        egui::ComboBox::from_id_salt("conversion-cmpr-quality")
+                            .selected_text(format!("Quality: {}", options.cmpr_quality))
+                            .show_ui(ui, |ui| {
+                                for q in CMPRQuality::iter() {
+                                    ui.selectable_value(
+                                        &mut options.cmpr_quality,
+                                        q,
+                                        q.to_string(),
+                                    );
+                                }
+                            });
+                    }
+                });
+
+                current.refresh(ui.ctx(), tex);
+                let converted = &current.converted.as_ref().unwrap().1;
+
+                ui.horizontal_top(|ui| {
+                    Self::draw_conversion_preview(
+                        ui,
+                        format!("Original ({}, {} bytes)", tex.header.data_format, tex.size),
+                        current.original.as_ref(),
+                    );
+
+                    match converted {
+                        Ok((converted, handle)) => Self::draw_conversion_preview(
+                            ui,
+                            format!(
+                                "Converted ({}, {} bytes)",
+                                converted.header.data_format, converted.size
+                            ),
+                            Ok(handle),
+                        ),
+                        Err(err) => {
+                            Self::draw_conversion_preview(ui, "Converted".to_string(), Err(err))
+                        }
+                    }
+                });
+
+                if let Ok((converted, _)) = converted {
+                    let change = i64::from(converted.size) - i64::from(tex.size);
+                    let comparison = match change.cmp(&0) {
+                        std::cmp::Ordering::Equal => "the same size as now".to_string(),
+                        std::cmp::Ordering::Greater => format!("{change} bytes more than now"),
+                        std::cmp::Ordering::Less => format!("{} bytes less than now", -change),
+                    };
+                    ui.label(format!(
+                        "The texture will take up {} bytes, {comparison}.",
+                        converted.size
+                    ));
+                }
+
+                if ui
+                    .add_enabled(converted.is_ok(), egui::Button::new("Apply"))
+                    .on_hover_text("Replaces the texture with the converted one, keeping its name.")
+                    .clicked()
+                {
+                    applied = true;
+                }
+            });
+
+        if applied {
+            if let Some((_, Ok((converted, _)))) = current.converted.take() {
+                let entry = &mut entries[current.index];
+                thumbnails.invalidate(&entry.texture);
+                entry.texture = converted;
+            }
+        }
+        if applied || !open {
+            *conversion = None;
+        }
+    }
+
+    /// Draws a preview of the format conversion window, showing the decoded texture in `image`
+    /// under `label`.
+    fn draw_conversion_preview(
+        ui: &mut egui::Ui,
+        label: String,
+        image: Result<&egui::TextureHandle, &String>,
+    ) {
+        ui.vertical(|ui| {
+            ui.label(label);
+            match image {
+                Ok(handle) => {
+                    let size = handle.size_vec2();
+                    let scale = CONVERSION_PREVIEW_SIZE / size.x.max(size.y);
+                    ui.image((handle.id(), size * scale));
+                }
+                Err(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            }
+        });
+    }
+
+    /// Reads the two GVR texture files in `paths` and compares them with each other.
+    fn compare_gvr_files(paths: &[std::path::PathBuf]) -> Result<TextureComparison, String> {
+        let [old_path, new_path] = paths else {
+            return Err("Exactly two GVR texture files have to be picked.".to_string());
+        };
+
+        let read = |path: &Path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let data = std::fs::read(path)
+                .map_err(|err| format!("File {file_name} could not be opened: {err}"))?;
+
+            GVRTexture::new_from_bytes(name, &data)
+                .map_err(|err| format!("File {file_name} is not a valid GVR texture: {err}."))
+        };
+
+        let (old, new) = (read(old_path)?, read(new_path)?);
+        Ok(TextureComparison::new(
+            format!("{} compared with {}", old.name, new.name),
+            &[&old],
+            &[&new],
+        ))
+    }
+
+    /// Draws the window showing the result of the last texture `comparison`, if there is one.
+    /// Closing the window discards the comparison.
+    fn draw_texture_comparison(ctx: &egui::Context, comparison: &mut Option<TextureComparison>) {
+        let Some(current) = comparison else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("Texture comparison")
+            .open(&mut open)
+            .default_size([700.0, 500.0])
+            .show(ctx, |ui| {
+                ui.label(&current.title);
+                ui.strong(current.summary());
+                ui.separator();
+
+                let mut shown_heatmap = current.heatmap.as_ref().map(|(row, _)| *row);
+
+                egui::ScrollArea::vertical()
+                    .max_height(ui.available_height() / 2.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        egui::Grid::new("texture-comparison-grid")
+                            .num_columns(6)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for heading in ["Old", "New", "Result", "PSNR", "Max error", ""] {
+                                    ui.strong(heading);
+                                }
+                                ui.end_row();
+
+                                for (i, row) in current.rows.iter().enumerate() {
+                                    let describe = |entry: &Option<(usize, String)>| match entry {
+                                        Some((index, name)) => format!("{index}. {name}"),
+                                        None => "-".to_string(),
+                                    };
+                                    ui.label(describe(&row.old));
+                                    ui.label(describe(&row.new));
+
+                                    let outcome = ui.label(row.outcome());
+                                    if let Some(diff) = &row.diff {
+                                        let mut details: Vec<String> = diff
+                                            .header_differences
+                                            .iter()
+                                            .map(|difference| difference.to_string())
+                                            .collect();
+                                        if let GVRVisualDiff::Undecodable(err) = &diff.visual {
+                                            details.push(err.to_string());
+                                        }
+                                        if !details.is_empty() {
+                                            outcome.on_hover_text(details.join("\n"));
+                                        }
+                                    }
+
+                                    match row.diff.as_ref().map(|diff| &diff.visual) {
+                                        Some(GVRVisualDiff::Compared(image_diff)) => {
+                                            if image_diff.psnr.is_infinite() {
+                                                ui.label("∞");
+                                            } else {
+                                                ui.label(format!("{:.2} dB", image_diff.psnr));
+                                            }
+                                            ui.label(image_diff.max_error.to_string());
+
+                                            let mut shown = shown_heatmap == Some(i);
+                                            if ui
+                                                .add_enabled(
+                                                    !image_diff.is_identical(),
+                                                    egui::Button::new("Heatmap").selected(shown),
+                                                )
+                                                .clicked()
+                                            {
+                                                shown = !shown;
+                                                shown_heatmap = shown.then_some(i);
+                                            }
+                                        }
+                                        _ => {
+                                            ui.label("-");
+                                            ui.label("-");
+                                            ui.label("");
+                                        }
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                // Load the heatmap of the newly picked row
+                if shown_heatmap != current.heatmap.as_ref().map(|(row, _)| *row) {
+                    current.heatmap = shown_heatmap.and_then(|row| {
+                        let Some(GVRVisualDiff::Compared(image_diff)) =
+                            current.rows[row].diff.as_ref().map(|diff| &diff.visual)
+                        else {
+                            return None;
+                        };
+
+                        let handle = load_rgba_image(
+                            ui.ctx(),
+                            "texture-comparison-heatmap",
+                            &image_diff.heatmap,
+                        );
+                        Some((row, handle))
+                    });
+                }
+
+                if let Some((_, handle)) = &current.heatmap {
+                    ui.separator();
+                    ui.label(
+                        "Difference heatmap, from black (unchanged) to white (biggest error):",
+                    );
+
+                    let size = handle.size_vec2();
+                    let available = ui.available_size();
+                    let scale = (available.x / size.x)
+                        .min(available.y / size.y)
+                        .max(f32::EPSILON);
+                    ui.image((handle.id(), size * scale));
+                }
+            });
+
+        if !open {
+            *comparison = None;
+        }
+    }
+
+    /// Draws a checkbox for every bit of the archive `flags` of a texture, named after the bit if
+    /// it's known.
+    fn draw_archive_flags(ui: &mut egui::Ui, flags: &mut u8) {
+        ui.horizontal_wrapped(|ui| {
+            ui.monospace(format!("0x{flags:02X}"));
+
+            for bit in 0..u8::BITS {
+                let mask = 1 << bit;
+                let name = ARCHIVE_FLAG_NAMES
+                    .iter()
+                    .find(|(known, _)| *known == mask)
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| format!("Bit {bit}"));
+
+                let mut set = *flags & mask != 0;
+                if ui.checkbox(&mut set, name).changed() {
+                    *flags ^= mask;
+                }
+            }
+        });
+    }
+
+    /// Paints a checkerboard filling `rect`, so that transparent pixels stand out.
+    fn draw_checkerboard(painter: &egui::Painter, rect: egui::Rect, dark_mode: bool) {
+        let (light, dark) = if dark_mode {
+            (Color32::from_gray(0x66), Color32::from_gray(0x44))
+        } else {
+            (Color32::from_gray(0xFF), Color32::from_gray(0xCC))
+        };
+        if !rect.is_positive() {
+            return;
+        }
+
+        painter.rect_filled(rect, 0.0, light);
+
+        let columns = (rect.width() / CHECKER_SIZE).ceil() as usize;
+        let rows = (rect.height() / CHECKER_SIZE).ceil() as usize;
+        for row in 0..rows {
+            for column in (row % 2..columns).step_by(2) {
+                let min = rect.min + egui::vec2(column as f32, row as f32) * CHECKER_SIZE;
+                let square = egui::Rect::from_min_size(min, egui::Vec2::splat(CHECKER_SIZE));
+                painter.rect_filled(square.intersect(rect), 0.0, dark);
+            }
+        }
+    }
+
+    /// Describes the header `flags` of a texture, along with the names of the known flags.
+    fn describe_flags(flags: u8) -> String {
+        let names: Vec<&str> = [
+            (GVR_FLAG_MIPMAPS, "mipmaps"),
+            (GVR_FLAG_EXTERNAL_PALETTE, "external palette"),
+            (GVR_FLAG_INTERNAL_PALETTE, "internal palette"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name)
+        .collect();
+
+        if names.is_empty() {
+            format!("0x{flags:02X}")
+        } else {
+            format!("0x{flags:02X} ({})", names.join(", "))
+        }
+    }
+
+    /// Describes the raw `texel` of a texture of the given `format`, in hexadecimal.
+    fn describe_texel(texel: GVRTexel, format: GVRDataFormat) -> String {
+        match texel {
+            GVRTexel::Value(value) => {
+                let digits = format.bits_per_pixel() as usize / 4;
+                let kind = if format.is_palettized() {
+                    "palette index "
+                } else {
+                    ""
+                };
+                format!("{kind}0x{value:0digits$X}")
+            }
+            GVRTexel::Cmpr {
+                color0,
+                color1,
+                index,
+            } => format!("color0 0x{color0:04X}, color1 0x{color1:04X}, index {index}"),
+        }
     }
 
     fn draw_home_tab(&mut self, _ctx: &egui::Context, ui: &mut egui::Ui) {
@@ -93,6 +1075,8 @@ impl EguiApp {
         let mut modal = Modal::new(ctx, "generic-texarc-dialog");
         modal.show_dialog();
 
+        Self::draw_texture_comparison(ctx, &mut self.texture_archive_ctx.comparison);
+
         ui.horizontal(|ui| {
             if ui
                 .button("Open file...")
@@ -102,11 +1086,63 @@ impl EguiApp {
                 .clicked()
             {
                 if let Some(path) = rfd::FileDialog::new().pick_file() {
-                    self.texture_archive_ctx.picked_file = Some(path.display().to_string());
+                    let picked_file = path.display().to_string();
 
-                    let tex_archive = TextureArchive::new(self.texture_archive_ctx.picked_file.clone().unwrap());
-                    if let Ok(archive) = tex_archive {
-                        self.texture_archive_ctx.archive = Some(archive);
+                    let tex_archive = TextureArchive::new(picked_file.clone());
+                    if let Ok(mut archive) = tex_archive {
+                        let mode = if self.texture_archive_ctx.salvage {
+                            TextureArchiveReadMode::Lenient
+                        } else {
+                            TextureArchiveReadMode::Strict
+                        };
+
+                        match archive.read_with_mode(mode) {
+                            Err(err) => {
+                                let hint = if mode == TextureArchiveReadMode::Strict {
+                                    "\n\nEnabling \"Salvage damaged archives\" may still open it."
+                                } else {
+                                    ""
+                                };
+
+                                modal
+                                    .dialog()
+                                    .with_title("Error")
+                                    .with_body(format!("{err}{hint}"))
+                                    .with_icon(Icon::Error)
+                                    .open();
+                            }
+                            Ok(report) => {
+                                let salvaged = Self::describe_diagnostics(&report.diagnostics);
+                                let converted = Self::describe_converted_variants(archive.textures());
+                                let violations = Self::describe_constraint_violations(archive.textures(), 0);
+                                let (title, icon) = if salvaged.is_some() {
+                                    ("Archive is damaged", Icon::Warning)
+                                } else if violations.is_some() {
+                                    ("Textures break hardware constraints", Icon::Warning)
+                                } else {
+                                    ("Textures converted", Icon::Info)
+                                };
+
+                                let reports: Vec<String> =
+                                    [salvaged, converted, violations].into_iter().flatten().collect();
+                                if !reports.is_empty() {
+                                    modal
+                                        .dialog()
+                                        .with_title(title)
+                                        .with_body(reports.join("\n\n"))
+                                        .with_icon(icon)
+                                        .open();
+                                }
+
+                                // Only replace the open archive once the new one was read
+                                self.texture_archive_ctx.picked_file = Some(picked_file);
+                                self.texture_archive_ctx.archive = Some(archive);
+                                self.texture_archive_ctx.thumbnails.clear();
+                                self.texture_archive_ctx.viewer = TextureViewer::default();
+                                self.texture_archive_ctx.conversion = None;
+                                self.texture_archive_ctx.violations.clear();
+                            }
+                        }
                     } else {
                         modal
                             .dialog()
@@ -115,22 +1151,22 @@ impl EguiApp {
                             .with_icon(Icon::Error)
                             .open();
                     }
-
-                    if let Err(err_str) = &self.texture_archive_ctx.archive.as_mut().unwrap().read() {
-                        modal
-                            .dialog()
-                            .with_title("Error")
-                            .with_body(err_str)
-                            .with_icon(Icon::Error)
-                            .open();
-                    }
                 }
             }
 
+            ui.checkbox(&mut self.texture_archive_ctx.salvage, "Salvage damaged archives")
+                .on_hover_ui(|ui| {
+                    ui.label("Opens archives with damaged textures anyway. The damaged entries are kept as raw data and written back when exporting.");
+                });
+
             if ui.button("Create new...").on_hover_ui(|ui| {
                 ui.label("Makes a new empty texture archive, where you can start adding textures into.");
             }).clicked() {
                 self.texture_archive_ctx.archive = Some(TextureArchive::new_empty());
+                self.texture_archive_ctx.thumbnails.clear();
+                self.texture_archive_ctx.viewer = TextureViewer::default();
+                self.texture_archive_ctx.conversion = None;
+                self.texture_archive_ctx.violations.clear();
             }
 
             let is_archive_exportable = self.texture_archive_ctx.archive.is_some()
@@ -138,7 +1174,7 @@ impl EguiApp {
                     .texture_archive_ctx.archive
                     .as_ref()
                     .unwrap()
-                    .textures
+                    .entries
                     .is_empty();
 
             if ui
@@ -151,19 +1187,24 @@ impl EguiApp {
                 .clicked()
             {
                 if let Some(rfd_path) = rfd::FileDialog::new().save_file() {
-                    if self
-                        .texture_archive_ctx.archive
-                        .as_ref()
-                        .unwrap()
-                        .export(&rfd_path.display().to_string())
-                        .is_ok()
-                    {
-                        modal
-                            .dialog()
-                            .with_title("Success")
-                            .with_body("Texture archive exported successfully!")
-                            .with_icon(Icon::Success)
-                            .open();
+                    let archive = self.texture_archive_ctx.archive.as_ref().unwrap();
+                    let violations = Self::describe_constraint_violations(archive.textures(), 0);
+
+                    if archive.export(&rfd_path.display().to_string()).is_ok() {
+                        match violations {
+                            Some(report) => modal
+                                .dialog()
+                                .with_title("Exported with warnings")
+                                .with_body(format!("Texture archive exported successfully, but some textures might not work in game.\n\n{report}"))
+                                .with_icon(Icon::Warning)
+                                .open(),
+                            None => modal
+                                .dialog()
+                                .with_title("Success")
+                                .with_body("Texture archive exported successfully!")
+                                .with_icon(Icon::Success)
+                                .open(),
+                        }
                     } else {
                         modal
                             .dialog()
@@ -174,6 +1215,68 @@ impl EguiApp {
                     }
                 }
             }
+
+            if ui
+                .add_enabled(
+                    self.texture_archive_ctx.archive.is_some(),
+                    egui::Button::new("Compare with archive..."),
+                )
+                .on_hover_ui(|ui| {
+                    ui.label("Compares the textures in the list with the textures of another texture archive, showing which ones have changed.");
+                })
+                .clicked()
+            {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    let other = std::fs::read(&path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|data| TextureArchive::from_bytes(&data).map_err(|err| err.to_string()));
+
+                    match other {
+                        Ok(other) => {
+                            let archive = self.texture_archive_ctx.archive.as_ref().unwrap();
+                            self.texture_archive_ctx.comparison = Some(TextureComparison::new(
+                                format!("Texture list compared with {}", path.display()),
+                                &archive.textures().collect::<Vec<_>>(),
+                                &other.textures().collect::<Vec<_>>(),
+                            ));
+                        }
+                        Err(err) => {
+                            modal
+                                .dialog()
+                                .with_title("Error")
+                                .with_body(format!("Texture archive could not be read: {err}"))
+                                .with_icon(Icon::Error)
+                                .open();
+                        }
+                    }
+                }
+            }
+
+            if ui
+                .button("Compare GVR files...")
+                .on_hover_ui(|ui| {
+                    ui.label("Compares two GVR texture files with each other. Pick the old texture first.");
+                })
+                .clicked()
+            {
+                if let Some(paths) = rfd::FileDialog::new()
+                    .add_filter("GVR textures", &["gvr"])
+                    .add_filter("All files", &["*"])
+                    .pick_files()
+                {
+                    match Self::compare_gvr_files(&paths) {
+                        Ok(comparison) => self.texture_archive_ctx.comparison = Some(comparison),
+                        Err(err) => {
+                            modal
+                                .dialog()
+                                .with_title("Error")
+                                .with_body(err)
+                                .with_icon(Icon::Error)
+                                .open();
+                        }
+                    }
+                }
+            }
         });
 
         if let Some(picked_file) = &self.texture_archive_ctx.picked_file {
@@ -197,57 +1300,74 @@ impl EguiApp {
                 if ui
                     .button("Add")
                     .on_hover_ui(|ui| {
-                        ui.label("Adds a new GVR texture(s) to the end of the texture list.");
+                        ui.label("Adds new GVR texture(s) or PNG, DDS and TGA image(s) to the end of the texture list.");
                     })
                     .clicked()
                 {
-                    if let Some(files) = rfd::FileDialog::new().pick_files() {
-                        let mut broken_file: Option<String> = None;
+                    if let Some(files) = rfd::FileDialog::new()
+                        .add_filter("Textures", &["gvr", "png", "dds", "tga"])
+                        .add_filter("All files", &["*"])
+                        .pick_files()
+                    {
+                        let options = GVREncodeOptions::new(self.texture_archive_ctx.image_format);
+                        let mut error: Option<String> = None;
+                        let first_added = tex_archive.entries.len();
 
                         for file in files {
-                            let path = file.display().to_string();
-                            let mut cursor = Cursor::new(std::fs::read(&path).unwrap());
-                            let texture = GVRTexture::new_from_cursor(
-                                file.file_stem()
-                                    .unwrap()
-                                    .to_os_string()
-                                    .into_string()
-                                    .unwrap(),
-                                &mut cursor,
-                            );
-
-                            if let Ok(valid_tex) = texture {
-                                tex_archive.textures.push(valid_tex);
-                            } else {
-                                broken_file = Some(
-                                    file.file_name()
-                                        .unwrap()
-                                        .to_os_string()
-                                        .into_string()
-                                        .unwrap(),
-                                );
-                                break;
+                            match Self::read_texture_file(&file, &options) {
+                                Ok(texture) => tex_archive.entries.push(TextureArchiveEntry::new(texture)),
+                                Err(err) => {
+                                    error = Some(err);
+                                    break;
+                                }
                             }
                         }
 
-                        if let Some(file) = broken_file {
+                        if let Some(err) = error {
                             modal
                                 .dialog()
                                 .with_title("Error")
-                                .with_body(format!("File {} is not a valid GVR texture.", file))
+                                .with_body(err)
                                 .with_icon(Icon::Error)
                                 .open();
                         } else {
+                            let mut message = String::from("Texture(s) added succesfully!");
+                            let mut icon = Icon::Success;
+                            let added = || tex_archive.textures().skip(first_added);
+                            if let Some(report) = Self::describe_converted_variants(added()) {
+                                message = format!("{message}\n\n{report}");
+                            }
+                            if let Some(report) = Self::describe_constraint_violations(added(), first_added) {
+                                message = format!("{message}\n\n{report}");
+                                icon = Icon::Warning;
+                            }
+
                             modal
                                 .dialog()
                                 .with_title("Success")
-                                .with_body("Texture(s) added succesfully!")
-                                .with_icon(Icon::Success)
+                                .with_body(message)
+                                .with_icon(icon)
                                 .open();
                         }
                     }
                 }
 
+                egui::ComboBox::from_id_salt("image-format")
+                    .selected_text(format!("Image format: {}", self.texture_archive_ctx.image_format))
+                    .show_ui(ui, |ui| {
+                        for format in GVRDataFormat::iter().filter(|f| f.is_encodable()) {
+                            ui.selectable_value(
+                                &mut self.texture_archive_ctx.image_format,
+                                format,
+                                format.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_ui(|ui| {
+                        ui.label("The GVR format that added PNG, DDS and TGA images are converted into.");
+                    });
+
                 if ui
                     .button("Extract all")
                     .on_hover_ui(|ui| {
@@ -273,8 +1393,92 @@ impl EguiApp {
                         }
                     }
                 }
+
+                if ui
+                    .button("Extract all as PNG")
+                    .on_hover_ui(|ui| {
+                        ui.label("Extracts all the textures in the current texture list into a folder as PNG images.");
+                    })
+                    .clicked()
+                {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        if let Err(err) = tex_archive.extract_all_as_png(&folder) {
+                            modal
+                                .dialog()
+                                .with_title("Error")
+                                .with_body(err)
+                                .with_icon(Icon::Error)
+                                .open();
+                        } else {
+                            modal
+                                .dialog()
+                                .with_title("Success")
+                                .with_body(format!("Textures extracted succesfully to: {}", folder.display()))
+                                .with_icon(Icon::Success)
+                                .open();
+                        }
+                    }
+                }
+
+                if ui
+                    .button("Export Dolphin pack")
+                    .on_hover_ui(|ui| {
+                        ui.label("Exports all the textures in the current texture list into a folder as a Dolphin custom texture pack.");
+                    })
+                    .clicked()
+                {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        let result = tex_archive.export_dolphin_pack(&folder);
+                        Self::show_dolphin_pack_report(&modal, result, "exported");
+                    }
+                }
+
+                if ui
+                    .button("Import Dolphin pack")
+                    .on_hover_ui(|ui| {
+                        ui.label("Replaces the textures in the current texture list with the edited images in a Dolphin custom texture pack folder.");
+                    })
+                    .clicked()
+                {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        let result = DolphinTexturePack::open(&folder)
+                            .and_then(|pack| tex_archive.import_dolphin_pack(&pack));
+                        Self::show_dolphin_pack_report(&modal, result, "replaced");
+                    }
+                }
             });
 
+            let thumbnails = &mut self.texture_archive_ctx.thumbnails;
+            let viewer = &mut self.texture_archive_ctx.viewer;
+            let conversion = &mut self.texture_archive_ctx.conversion;
+            let violations = &mut self.texture_archive_ctx.violations;
+
+            Self::draw_format_conversion(ctx, conversion, &mut tex_archive.entries, thumbnails);
+
+            if !tex_archive.damaged_entries.is_empty() {
+                let names: Vec<String> = tex_archive
+                    .damaged_entries
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{}. {} ({} bytes)",
+                            entry.index,
+                            entry.name,
+                            entry.data.len()
+                        )
+                    })
+                    .collect();
+
+                ui.label(
+                    egui::RichText::new(format!(
+                        "⚠ {} damaged entries are kept as raw data, and are written back when exporting.",
+                        tex_archive.damaged_entries.len()
+                    ))
+                    .color(ui.visuals().warn_fg_color),
+                )
+                .on_hover_text(names.join("\n"));
+            }
+
             egui::ScrollArea::vertical()
                 .auto_shrink(false)
                 .drag_to_scroll(false)
@@ -284,19 +1488,78 @@ impl EguiApp {
                     let mut moved_down_index: Option<usize> = None;
                     let mut duplicated_index: Option<usize> = None;
                     let mut moved_index: Option<(usize, usize)> = None;
+                    let mut replaced: Option<(usize, std::path::PathBuf, GVRDataFormat)> = None;
+                    let mut converted_index: Option<usize> = None;
 
-                    let textures_count = tex_archive.textures.len();
-                    for (i, tex) in tex_archive.textures.iter_mut().enumerate() {
+                    let textures_count = tex_archive.entries.len();
+                    let has_flags = tex_archive.is_without_model;
+                    for (i, entry) in tex_archive.entries.iter_mut().enumerate() {
+                        let TextureArchiveEntry { texture: tex, flags, .. } = entry;
                         ui.horizontal(|ui| {
                             ui.scope(|ui| {
                                 ui.style_mut().interaction.selectable_labels = false;
                                 ui.add_sized([40.0, 20.0], egui::Label::new(format!("{i}.")));
                             });
 
+                            let selected = viewer.selected == Some(i);
+                            let thumbnail = Self::draw_thumbnail(ui, thumbnails, tex, selected);
+                            if thumbnail.clicked() {
+                                viewer.select(i);
+                            }
+                            thumbnail.context_menu(|ui| {
+                                if ui.button("Open in viewer").clicked() {
+                                    viewer.select(i);
+                                    ui.close_menu();
+                                }
+                                if ui
+                                    .button("Convert format...")
+                                    .on_hover_text("Re-encodes this texture into another format, with a preview before applying it.")
+                                    .clicked()
+                                {
+                                    converted_index = Some(i);
+                                    ui.close_menu();
+                                }
+                            });
+                            ui.scope(|ui| {
+                                ui.style_mut().interaction.selectable_labels = false;
+                                ui.add_sized(
+                                    [70.0, THUMBNAIL_SIZE],
+                                    egui::Label::new(
+                                        egui::RichText::new(format!(
+                                            "{}\n{}x{}",
+                                            tex.header.data_format, tex.header.width, tex.header.height
+                                        ))
+                                        .small(),
+                                    ),
+                                );
+                            });
+
+                            let tex_violations = violations
+                                .entry(ThumbnailCache::key(tex))
+                                .or_insert_with(|| tex.check_constraints());
+                            if !tex_violations.is_empty() {
+                                let list: Vec<String> =
+                                    tex_violations.iter().map(|v| format!("• {v}")).collect();
+                                ui.label(egui::RichText::new("⚠").color(ui.visuals().warn_fg_color))
+                                    .on_hover_text(format!(
+                                        "This texture breaks GameCube hardware constraints:\n{}",
+                                        list.join("\n")
+                                    ));
+                            }
+
                             let _ = ui.add(
                                 egui::TextEdit::singleline(&mut tex.name).hint_text("Texture name"),
                             );
 
+                            if has_flags {
+                                ui.add(
+                                    egui::DragValue::new(flags)
+                                        .hexadecimal(2, false, true)
+                                        .prefix("0x"),
+                                )
+                                .on_hover_text("The flags stored for this texture in the archive. Open the texture in the viewer to edit the single bits.");
+                            }
+
                             ui.spacing_mut().button_padding = [1., 0.].into();
                             ui.scope(|ui| {
                                 ui.style_mut().spacing.item_spacing = [10., 0.].into();
@@ -339,6 +1602,60 @@ impl EguiApp {
                                 duplicated_index = Some(i);
                             }
 
+                            let replace_response = ui.button("Replace...").on_hover_ui(|ui| {
+                                ui.label("Replaces this texture with a GVR texture or a PNG, DDS or TGA image, keeping its name.");
+                            });
+                            let replace_popup_id = ui.make_persistent_id(format!("replace_btn_{i}"));
+                            if replace_response.clicked() {
+                                ui.memory_mut(|mem| mem.toggle_popup(replace_popup_id));
+                            }
+
+                            egui::popup::popup_above_or_below_widget(
+                                ui,
+                                replace_popup_id,
+                                &replace_response,
+                                egui::AboveOrBelow::Below,
+                                egui::popup::PopupCloseBehavior::CloseOnClickOutside,
+                                |ui| {
+                                    ui.set_min_width(200.0);
+
+                                    // Images default to the format of the texture they replace
+                                    let mem_id = egui::Id::new(format!("replace_format_{i}"));
+                                    let default_format = if tex.header.data_format.is_encodable() {
+                                        tex.header.data_format
+                                    } else {
+                                        GVRDataFormat::default()
+                                    };
+                                    let mut format = ui.memory_mut(|mem| {
+                                        *mem.data.get_temp_mut_or(mem_id, default_format)
+                                    });
+
+                                    egui::ComboBox::from_id_salt(format!("replace_format_combo_{i}"))
+                                        .selected_text(format!("Image format: {format}"))
+                                        .show_ui(ui, |ui| {
+                                            for f in GVRDataFormat::iter().filter(|f| f.is_encodable()) {
+                                                ui.selectable_value(&mut format, f, f.to_string());
+                                            }
+                                        });
+                                    ui.memory_mut(|mem| mem.data.insert_temp(mem_id, format));
+
+                                    if ui.button("Choose file...").clicked() {
+                                        if let Some(path) = rfd::FileDialog::new()
+                                            .add_filter("Textures", &["gvr", "png", "dds", "tga"])
+                                            .add_filter("All files", &["*"])
+                                            .pick_file()
+                                        {
+                                            replaced = Some((i, path, format));
+                                        }
+
+                                        ui.memory_mut(|mem| {
+                                            mem.data.remove_temp::<GVRDataFormat>(mem_id);
+                                            mem.close_popup();
+                                        });
+                                    }
+                                },
+                            );
+
                             let move_response = ui.button("Move to...");
                             let popup_id = ui.make_persistent_id(format!("move_btn_{i}"));
                             if move_response.clicked() {
@@ -388,35 +1705,265 @@ impl EguiApp {
                     }
 
                     if let Some(idx) = removed_index {
-                        tex_archive.textures.remove(idx);
+                        tex_archive.entries.remove(idx);
+                        viewer.follow_shift(idx, false);
                     }
                     if let Some(idx) = moved_up_index {
-                        if idx == 0 {
-                            tex_archive.textures.swap(idx, textures_count - 1);
-                        } else {
-                            tex_archive.textures.swap(idx, idx - 1);
-                        }
+                        let swapped_idx = if idx == 0 { textures_count - 1 } else { idx - 1 };
+                        tex_archive.entries.swap(idx, swapped_idx);
+                        viewer.follow_swap(idx, swapped_idx);
                     }
                     if let Some(idx) = moved_down_index {
-                        if idx == textures_count - 1 {
-                            tex_archive.textures.swap(idx, 0);
-                        } else {
-                            tex_archive.textures.swap(idx, idx + 1);
-                        }
+                        let swapped_idx = if idx == textures_count - 1 { 0 } else { idx + 1 };
+                        tex_archive.entries.swap(idx, swapped_idx);
+                        viewer.follow_swap(idx, swapped_idx);
                     }
                     if let Some(idx) = duplicated_index {
-                        let mut dup_texture = tex_archive.textures[idx].clone();
-                        dup_texture.name += "_duplicate";
+                        let mut dup_entry = tex_archive.entries[idx].clone();
+                        dup_entry.texture.name += "_duplicate";
 
-                        tex_archive.textures.insert(idx + 1, dup_texture);
+                        tex_archive.entries.insert(idx + 1, dup_entry);
+                        viewer.follow_shift(idx + 1, true);
                     }
                     if let Some((idx, moved_to_idx)) = moved_index {
-                        tex_archive.textures.swap(idx, moved_to_idx);
+                        tex_archive.entries.swap(idx, moved_to_idx);
+                        viewer.follow_swap(idx, moved_to_idx);
+                    }
+                    if let Some(idx) = converted_index {
+                        *conversion =
+                            Some(FormatConversion::new(ui.ctx(), &tex_archive.entries[idx].texture, idx));
+                    }
+                    if let Some((idx, path, format)) = replaced {
+                        let old_texture = &tex_archive.entries[idx].texture;
+                        let mut options = GVREncodeOptions::matching(old_texture);
+                        options.format = format;
+
+                        match Self::read_texture_file(&path, &options) {
+                            Ok(mut texture) => {
+                                texture.name = old_texture.name.clone();
+                                thumbnails.invalidate(old_texture);
+                                tex_archive.entries[idx].texture = texture;
+                            }
+                            Err(err) => {
+                                modal
+                                    .dialog()
+                                    .with_title("Error")
+                                    .with_body(err)
+                                    .with_icon(Icon::Error)
+                                    .open();
+                            }
+                        }
                     }
                 });
         }
     }
 
+    /// Draws the thumbnail of `tex` from `thumbnails`, fitted into a square, and outlined if the
+    /// texture is `selected`. The texture is only decoded once the thumbnail is actually visible.
+    /// Clicking the thumbnail opens the texture in the texture viewer.
+    fn draw_thumbnail(
+        ui: &mut egui::Ui,
+        thumbnails: &mut ThumbnailCache,
+        tex: &GVRTexture,
+        selected: bool,
+    ) -> egui::Response {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::splat(THUMBNAIL_SIZE), egui::Sense::click());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        ui.painter()
+            .rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        if selected {
+            ui.painter()
+                .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+        }
+
+        match thumbnails.get(ui.ctx(), tex) {
+            Ok(handle) => {
+                let size = handle.size_vec2();
+                let scale = THUMBNAIL_SIZE / size.x.max(size.y);
+                let image_rect = egui::Rect::from_center_size(rect.center(), size * scale);
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+
+                ui.painter()
+                    .image(handle.id(), image_rect, uv, Color32::WHITE);
+            }
+            Err(err) => {
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "?",
+                    egui::FontId::proportional(20.0),
+                    ui.visuals().weak_text_color(),
+                );
+                return response.on_hover_text(format!("No preview: {err}"));
+            }
+        }
+
+        response.on_hover_text("Click to open in the texture viewer, right-click for more options.")
+    }
+
+    /// Reads the texture file in `path` for adding it into a texture archive. PNG, DDS and TGA
+    /// images are converted into a GVR texture as per `options`, anything else has to be a valid
+    /// GVR texture.
+    fn read_texture_file(path: &Path, options: &GVREncodeOptions) -> Result<GVRTexture, String> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        let data = std::fs::read(path)
+            .map_err(|err| format!("File {file_name} could not be opened: {err}"))?;
+
+        let extension = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        let converted = match extension.as_str() {
+            "png" => GVRTexture::from_png(name, &data, options),
+            "dds" => GVRTexture::from_dds(name, &data, options),
+            "tga" => GVRTexture::from_tga(name, &data, options),
+            _ => {
+                return GVRTexture::new_from_bytes(name, &data)
+                    .map_err(|err| format!("File {file_name} is not a valid GVR texture: {err}."));
+            }
+        };
+
+        converted.map_err(|err| format!("File {file_name} could not be converted: {err}"))
+    }
+
+    /// Lists the given `textures` that break GX hardware constraints, along with the constraints
+    /// they break. `first_index` is the index of the first texture in the texture list. Returns
+    /// [`None`] if there aren't any.
+    fn describe_constraint_violations<'a>(
+        textures: impl Iterator<Item = &'a GVRTexture>,
+        first_index: usize,
+    ) -> Option<String> {
+        let lines: Vec<String> = textures
+            .enumerate()
+            .filter_map(|(i, tex)| {
+                let violations = tex.check_constraints();
+                if violations.is_empty() {
+                    return None;
+                }
+
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                Some(format!(
+                    "{}. {}: {}",
+                    first_index + i,
+                    tex.name,
+                    violations.join("; ")
+                ))
+            })
+            .collect();
+
+        if lines.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "The following texture(s) break GameCube hardware constraints:\n{}",
+            lines.join("\n")
+        ))
+    }
+
+    /// Lists the damaged entries that were salvaged while reading a texture archive, described by
+    /// the `diagnostics` of the read. Returns [`None`] if there aren't any.
+    fn describe_diagnostics(diagnostics: &[TextureArchiveError]) -> Option<String> {
+        if diagnostics.is_empty() {
+            return None;
+        }
+
+        let lines: Vec<String> = diagnostics.iter().map(|err| err.to_string()).collect();
+        Some(format!(
+            "The archive was opened, but some of its entries are damaged. Damaged textures are kept as raw data, and are written back when exporting.\n{}",
+            lines.join("\n")
+        ))
+    }
+
+    /// Lists the given `textures` that weren't GCIX-headed when they were read, and have been
+    /// converted into GCIX-headed textures. Returns [`None`] if there aren't any.
+    fn describe_converted_variants<'a>(
+        textures: impl Iterator<Item = &'a GVRTexture>,
+    ) -> Option<String> {
+        let converted: Vec<String> = textures
+            .filter(|tex| tex.source_variant != GVRVariant::GCIX)
+            .map(|tex| format!("{} ({})", tex.name, tex.source_variant))
+            .collect();
+
+        if converted.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "The following texture(s) were converted to GCIX-headed textures: {}",
+            converted.join(", ")
+        ))
+    }
+
+    /// Shows the outcome of exporting or importing a Dolphin custom texture pack, where
+    /// `processed` describes what happened to the textures that were handled.
+    fn show_dolphin_pack_report(
+        modal: &Modal,
+        result: std::io::Result<DolphinPackReport>,
+        processed: &str,
+    ) {
+        let report = match result {
+            Ok(report) => report,
+            Err(err) => {
+                modal
+                    .dialog()
+                    .with_title("Error")
+                    .with_body(err)
+                    .with_icon(Icon::Error)
+                    .open();
+                return;
+            }
+        };
+
+        let mut message = format!(
+            "{} texture(s) {processed} succesfully!",
+            report.processed.len()
+        );
+        if report.failed.is_empty() && report.violations.is_empty() {
+            modal
+                .dialog()
+                .with_title("Success")
+                .with_body(message)
+                .with_icon(Icon::Success)
+                .open();
+            return;
+        }
+
+        if !report.failed.is_empty() {
+            message += "\n\nThe following texture(s) were skipped:";
+            for (name, err) in &report.failed {
+                message += &format!("\n{name}: {err}");
+            }
+        }
+
+        if !report.violations.is_empty() {
+            message += "\n\nThe following texture(s) break GameCube hardware constraints:";
+            for (name, violations) in &report.violations {
+                let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                message += &format!("\n{name}: {}", violations.join("; "));
+            }
+        }
+
+        modal
+            .dialog()
+            .with_title("Warning")
+            .with_body(message)
+            .with_icon(Icon::Warning)
+            .open();
+    }
+
     fn draw_graphical_archive_tab(&mut self, _ctx: &egui::Context, ui: &mut egui::Ui) {
         if ui.button("Open").clicked() {
             if let Some(path) = rfd::FileDialog::new().pick_file() {
@@ -490,6 +2037,36 @@ impl EguiApp {
                     }
                 }
             }
+
+            let has_archive = self.packman_archive_ctx.archive.is_some();
+            if ui
+                .add_enabled(has_archive, egui::Button::new("Export Dolphin pack"))
+                .on_hover_ui(|ui| {
+                    ui.label("Exports the textures of all the texture archives in this archive into a folder as a Dolphin custom texture pack.");
+                })
+                .clicked()
+            {
+                if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                    let archive = self.packman_archive_ctx.archive.as_ref().unwrap();
+                    let result = archive.export_dolphin_pack(&folder);
+                    Self::show_dolphin_pack_report(modal, result, "exported");
+                }
+            }
+
+            if ui
+                .add_enabled(has_archive, egui::Button::new("Import Dolphin pack"))
+                .on_hover_ui(|ui| {
+                    ui.label("Replaces the textures of all the texture archives in this archive with the edited images in a Dolphin custom texture pack folder.");
+                })
+                .clicked()
+            {
+                if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                    let archive = self.packman_archive_ctx.archive.as_mut().unwrap();
+                    let result = DolphinTexturePack::open(&folder)
+                        .and_then(|pack| archive.import_dolphin_pack(&pack));
+                    Self::show_dolphin_pack_report(modal, result, "replaced");
+                }
+            }
         });
     }
 
@@ -0,0 +1,518 @@
+//! This module contains the functionality to work with CMPR texture data, which is the GameCube
+//! variant of S3TC/DXT1 compression.
+//!
+//! CMPR textures are made of 8x8 blocks, each containing 4 DXT1 sub-blocks of 4x4 pixels in
+//! top-left, top-right, bottom-left and bottom-right order. Unlike regular DXT1, both color
+//! endpoints are stored in big-endian, and the 2-bit indices of each row are stored with the
+//! leftmost pixel in the most significant bits.
+
+use super::{
+    decode::rgb565_to_rgba,
+    encode::{rgba_to_rgb565, CMPRQuality},
+};
+
+/// The size of a single 4x4 DXT1 sub-block in bytes.
+pub(crate) const SUB_BLOCK_SIZE: usize = 8;
+
+/// Pixels with an alpha value below this are treated as transparent when encoding.
+const ALPHA_THRESHOLD: u8 = 0x80;
+
+/// The maximum amount of passes the endpoint search does in [`CMPRQuality::High`].
+const MAX_SEARCH_PASSES: usize = 16;
+
+/// Blends two channel values the way the GameCube hardware does, at a 5/8 weight for `a` and 3/8
+/// for `b`, which approximates the 2/3 and 1/3 weights of regular DXT1.
+fn blend_5_3(a: u8, b: u8) -> u8 {
+    ((a as u16 * 5 + b as u16 * 3) >> 3) as u8
+}
+
+/// Averages two channel values for the 3-color mode.
+fn average(a: u8, b: u8) -> u8 {
+    ((a as u16 + b as u16) >> 1) as u8
+}
+
+/// Builds the 4 color palette of a sub-block from its two RGB565 endpoints.
+///
+/// If the first endpoint is greater than the second, the palette consists of the endpoints and
+/// two colors in between them. Otherwise it consists of the endpoints, their average and a fully
+/// transparent color.
+pub(crate) fn sub_block_palette(color0: u16, color1: u16) -> [[u8; 4]; 4] {
+    let c0 = rgb565_to_rgba(color0);
+    let c1 = rgb565_to_rgba(color1);
+
+    let mut palette = [c0, c1, [0; 4], [0; 4]];
+
+    if color0 > color1 {
+        for ch in 0..3 {
+            palette[2][ch] = blend_5_3(c0[ch], c1[ch]);
+            palette[3][ch] = blend_5_3(c1[ch], c0[ch]);
+        }
+        palette[2][3] = 0xFF;
+        palette[3][3] = 0xFF;
+    } else {
+        for ch in 0..3 {
+            palette[2][ch] = average(c0[ch], c1[ch]);
+        }
+        palette[2][3] = 0xFF;
+    }
+
+    palette
+}
+
+/// Decodes a single 8 byte DXT1 sub-block into 16 pixels in row-major order.
+fn decode_sub_block(sub_block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let color0 = u16::from_be_bytes([sub_block[0], sub_block[1]]);
+    let color1 = u16::from_be_bytes([sub_block[2], sub_block[3]]);
+    let palette = sub_block_palette(color0, color1);
+
+    for (y, &row) in sub_block[4..8].iter().enumerate() {
+        for x in 0..4 {
+            let idx = (row >> (6 - x * 2)) & 0x3;
+            out[y * 4 + x] = palette[idx as usize];
+        }
+    }
+}
+
+/// Decodes a single 32 byte CMPR block into the 64 pixels of the 8x8 block in row-major order.
+pub(crate) fn decode_cmpr_block(block: &[u8], out: &mut [[u8; 4]]) {
+    let mut sub_pixels = [[0; 4]; 16];
+
+    for (i, sub_block) in block.chunks_exact(SUB_BLOCK_SIZE).enumerate() {
+        let sub_x = (i % 2) * 4;
+        let sub_y = (i / 2) * 4;
+
+        decode_sub_block(sub_block, &mut sub_pixels);
+
+        for (j, color) in sub_pixels.iter().enumerate() {
+            out[(sub_y + j / 4) * 8 + sub_x + j % 4] = *color;
+        }
+    }
+}
+
+/// Encodes the 64 pixels of an 8x8 block, given in row-major order, into a single 32 byte CMPR
+/// block.
+pub(crate) fn encode_cmpr_block(pixels: &[[u8; 4]], out: &mut [u8], quality: CMPRQuality) {
+    let mut sub_pixels = [[0; 4]; 16];
+
+    for (i, sub_block) in out.chunks_exact_mut(SUB_BLOCK_SIZE).enumerate() {
+        let sub_x = (i % 2) * 4;
+        let sub_y = (i / 2) * 4;
+
+        for (j, color) in sub_pixels.iter_mut().enumerate() {
+            *color = pixels[(sub_y + j / 4) * 8 + sub_x + j % 4];
+        }
+
+        sub_block.copy_from_slice(&encode_sub_block(&sub_pixels, quality));
+    }
+}
+
+/// Encodes 16 pixels in row-major order into a single 8 byte DXT1 sub-block.
+///
+/// Sub-blocks containing any transparent pixels are always encoded in the 3-color mode, with the
+/// transparent pixels using the transparent color. Fully opaque sub-blocks use the 4-color mode,
+/// unless [`CMPRQuality::High`] finds the 3-color mode to be more accurate.
+fn encode_sub_block(pixels: &[[u8; 4]; 16], quality: CMPRQuality) -> [u8; 8] {
+    let has_transparency = pixels.iter().any(|p| p[3] < ALPHA_THRESHOLD);
+    let opaque: Vec<[u8; 4]> = pixels
+        .iter()
+        .copied()
+        .filter(|p| p[3] >= ALPHA_THRESHOLD)
+        .collect();
+
+    let (mut a, mut b) = if opaque.is_empty() {
+        (0, 0)
+    } else {
+        principal_axis_endpoints(&opaque)
+    };
+
+    let mut three_color = has_transparency || a == b;
+
+    if quality == CMPRQuality::High && !opaque.is_empty() {
+        let mut best_error = u32::MAX;
+        let modes: &[bool] = if has_transparency {
+            &[true]
+        } else {
+            &[false, true]
+        };
+
+        for &mode in modes {
+            let (mode_a, mode_b) = refine_endpoints(pixels, a, b, mode);
+            let (mode_a, mode_b) = search_endpoints(pixels, mode_a, mode_b, mode);
+            let error = block_error(pixels, mode_a, mode_b, mode);
+
+            if error < best_error {
+                best_error = error;
+                (a, b, three_color) = (mode_a, mode_b, mode || mode_a == mode_b);
+            }
+        }
+    }
+
+    let (color0, color1) = order_endpoints(a, b, three_color);
+    let (_, indices) = match_palette(pixels, &sub_block_palette(color0, color1), three_color);
+
+    let mut out = [0; 8];
+    out[0..2].copy_from_slice(&color0.to_be_bytes());
+    out[2..4].copy_from_slice(&color1.to_be_bytes());
+
+    for (y, row) in out[4..8].iter_mut().enumerate() {
+        // The leftmost pixel is stored in the most significant bits
+        *row = indices[y * 4..y * 4 + 4]
+            .iter()
+            .fold(0, |acc, &idx| (acc << 2) | idx);
+    }
+
+    out
+}
+
+/// Orders the two RGB565 endpoints so that the decoder picks the wanted mode. The 4-color mode
+/// requires the first endpoint to be greater than the second, and the 3-color mode the opposite.
+fn order_endpoints(a: u16, b: u16, three_color: bool) -> (u16, u16) {
+    if three_color == (a <= b) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Finds the closest palette color for every pixel, returning the total squared error of the
+/// opaque pixels and the chosen indices.
+///
+/// In the 3-color mode, transparent pixels always use the transparent color at index 3, which
+/// opaque pixels never use.
+fn match_palette(
+    pixels: &[[u8; 4]; 16],
+    palette: &[[u8; 4]; 4],
+    three_color: bool,
+) -> (u32, [u8; 16]) {
+    let color_count = if three_color { 3 } else { 4 };
+    let mut total_error = 0;
+    let mut indices = [0; 16];
+
+    for (pixel, index) in pixels.iter().zip(indices.iter_mut()) {
+        if three_color && pixel[3] < ALPHA_THRESHOLD {
+            *index = 3;
+            continue;
+        }
+
+        let (best_idx, best_error) = palette[..color_count]
+            .iter()
+            .map(|color| color_distance(pixel, color))
+            .enumerate()
+            .min_by_key(|&(_, error)| error)
+            .unwrap();
+
+        *index = best_idx as u8;
+        total_error += best_error;
+    }
+
+    (total_error, indices)
+}
+
+/// Calculates the error of encoding `pixels` with the given endpoints and mode.
+fn block_error(pixels: &[[u8; 4]; 16], a: u16, b: u16, three_color: bool) -> u32 {
+    if !three_color && a == b {
+        // The 4-color mode can't be represented with equal endpoints
+        return u32::MAX;
+    }
+
+    let (color0, color1) = order_endpoints(a, b, three_color);
+    match_palette(pixels, &sub_block_palette(color0, color1), three_color).0
+}
+
+/// Calculates the squared distance between the RGB components of two colors.
+fn color_distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    (0..3)
+        .map(|ch| (a[ch] as i32 - b[ch] as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Picks two endpoints by projecting all the colors onto their principal axis, and taking the
+/// colors at both extremes.
+fn principal_axis_endpoints(colors: &[[u8; 4]]) -> (u16, u16) {
+    let count = colors.len() as f32;
+    let mut mean = [0f32; 3];
+    for color in colors {
+        for ch in 0..3 {
+            mean[ch] += color[ch] as f32 / count;
+        }
+    }
+
+    // Covariance matrix of the colors
+    let mut cov = [[0f32; 3]; 3];
+    for color in colors {
+        let d: Vec<f32> = (0..3).map(|ch| color[ch] as f32 - mean[ch]).collect();
+        for i in 0..3 {
+            for j in 0..3 {
+                cov[i][j] += d[i] * d[j];
+            }
+        }
+    }
+
+    // Power iteration to find the dominant eigenvector
+    let mut axis = [1f32, 1., 1.];
+    for _ in 0..8 {
+        let next: Vec<f32> = (0..3)
+            .map(|i| (0..3).map(|j| cov[i][j] * axis[j]).sum())
+            .collect();
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < f32::EPSILON {
+            break;
+        }
+        for i in 0..3 {
+            axis[i] = next[i] / length;
+        }
+    }
+
+    let project = |color: &[u8; 4]| -> f32 {
+        (0..3)
+            .map(|ch| (color[ch] as f32 - mean[ch]) * axis[ch])
+            .sum()
+    };
+
+    let min = colors
+        .iter()
+        .min_by(|a, b| project(a).total_cmp(&project(b)))
+        .unwrap();
+    let max = colors
+        .iter()
+        .max_by(|a, b| project(a).total_cmp(&project(b)))
+        .unwrap();
+
+    (rgba_to_rgb565(*max), rgba_to_rgb565(*min))
+}
+
+/// Refines the endpoints with a least squares fit, based on the palette indices the current
+/// endpoints result in. The refined endpoints are only kept if they reduce the error.
+fn refine_endpoints(pixels: &[[u8; 4]; 16], a: u16, b: u16, three_color: bool) -> (u16, u16) {
+    let mut best = (a, b);
+    let mut best_error = block_error(pixels, a, b, three_color);
+
+    for _ in 0..2 {
+        let (color0, color1) = order_endpoints(best.0, best.1, three_color);
+        let (_, indices) = match_palette(pixels, &sub_block_palette(color0, color1), three_color);
+
+        // The weight of the first endpoint for each palette index
+        let weights: [f32; 4] = if three_color {
+            [1., 0., 0.5, 0.]
+        } else {
+            [1., 0., 5. / 8., 3. / 8.]
+        };
+
+        let (mut aa, mut bb, mut ab) = (0f32, 0f32, 0f32);
+        let mut ax = [0f32; 3];
+        let mut bx = [0f32; 3];
+
+        for (pixel, &index) in pixels.iter().zip(&indices) {
+            if three_color && index == 3 {
+                continue;
+            }
+
+            let w = weights[index as usize];
+            aa += w * w;
+            bb += (1. - w) * (1. - w);
+            ab += w * (1. - w);
+            for ch in 0..3 {
+                ax[ch] += w * pixel[ch] as f32;
+                bx[ch] += (1. - w) * pixel[ch] as f32;
+            }
+        }
+
+        let det = aa * bb - ab * ab;
+        if det.abs() < f32::EPSILON {
+            break;
+        }
+
+        let mut new0 = [0, 0, 0, 0xFF];
+        let mut new1 = [0, 0, 0, 0xFF];
+        for ch in 0..3 {
+            new0[ch] = ((ax[ch] * bb - bx[ch] * ab) / det).round().clamp(0., 255.) as u8;
+            new1[ch] = ((bx[ch] * aa - ax[ch] * ab) / det).round().clamp(0., 255.) as u8;
+        }
+
+        let candidate = (rgba_to_rgb565(new0), rgba_to_rgb565(new1));
+        let error = block_error(pixels, candidate.0, candidate.1, three_color);
+        if error >= best_error {
+            break;
+        }
+
+        best = candidate;
+        best_error = error;
+    }
+
+    best
+}
+
+/// Searches the neighbouring RGB565 values of both endpoints, one channel step at a time, and
+/// keeps any change that reduces the error until no more improvements are found.
+fn search_endpoints(pixels: &[[u8; 4]; 16], a: u16, b: u16, three_color: bool) -> (u16, u16) {
+    // Bit offset and maximum value of each RGB565 channel
+    const CHANNELS: [(u16, u16); 3] = [(11, 0x1F), (5, 0x3F), (0, 0x1F)];
+
+    let mut endpoints = [a, b];
+    let mut best_error = block_error(pixels, a, b, three_color);
+
+    for _ in 0..MAX_SEARCH_PASSES {
+        let mut improved = false;
+
+        for endpoint in 0..2 {
+            for (shift, max) in CHANNELS {
+                for step in [-1i32, 1] {
+                    let value = (endpoints[endpoint] >> shift) & max;
+                    let new_value = value as i32 + step;
+                    if new_value < 0 || new_value > max as i32 {
+                        continue;
+                    }
+
+                    let mut candidate = endpoints;
+                    candidate[endpoint] =
+                        (candidate[endpoint] & !(max << shift)) | ((new_value as u16) << shift);
+
+                    let error = block_error(pixels, candidate[0], candidate[1], three_color);
+                    if error < best_error {
+                        best_error = error;
+                        endpoints = candidate;
+                        improved = true;
+                    }
+                }
+            }
+        }
+
+        if !improved || best_error == 0 {
+            break;
+        }
+    }
+
+    (endpoints[0], endpoints[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::{
+        decode::decode_texture_data,
+        encode::{encode_texture_data, GVREncodeOptions},
+        image::RGBAImage,
+        GVRDataFormat,
+    };
+
+    fn encode_with(image: &RGBAImage, quality: CMPRQuality) -> Vec<u8> {
+        let options = GVREncodeOptions {
+            cmpr_quality: quality,
+            ..GVREncodeOptions::new(GVRDataFormat::CMPR)
+        };
+        encode_texture_data(image, &options).unwrap()
+    }
+
+    fn total_error(image: &RGBAImage, data: &[u8]) -> u64 {
+        let decoded =
+            decode_texture_data(GVRDataFormat::CMPR, data, image.width, image.height, None)
+                .unwrap();
+        image
+            .pixels
+            .iter()
+            .zip(&decoded.pixels)
+            .map(|(&a, &b)| (a as i64 - b as i64).pow(2) as u64)
+            .sum()
+    }
+
+    #[test]
+    fn decode_four_color_mode() {
+        // Red and blue endpoints, the first row uses indices 0, 1, 2, 3 from left to right
+        let mut data = vec![0; 32];
+        data[0..8].copy_from_slice(&[0xF8, 0x00, 0x00, 0x1F, 0b00_01_10_11, 0, 0, 0]);
+
+        let image = decode_texture_data(GVRDataFormat::CMPR, &data, 8, 8, None).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(1, 0), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(2, 0), [0x9F, 0, 0x5F, 0xFF]);
+        assert_eq!(image.get_pixel(3, 0), [0x5F, 0, 0x9F, 0xFF]);
+        assert_eq!(image.get_pixel(0, 1), [0xFF, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn decode_three_color_mode() {
+        // Black and white endpoints with color0 <= color1
+        let mut data = vec![0; 32];
+        data[0..8].copy_from_slice(&[0x00, 0x00, 0xFF, 0xFF, 0b10_11_00_01, 0, 0, 0]);
+
+        let image = decode_texture_data(GVRDataFormat::CMPR, &data, 8, 8, None).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0x7F, 0x7F, 0x7F, 0xFF]);
+        assert_eq!(image.get_pixel(1, 0), [0; 4]);
+        assert_eq!(image.get_pixel(2, 0), [0, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(3, 0), [0xFF; 4]);
+    }
+
+    #[test]
+    fn decode_sub_block_order() {
+        // Each sub-block is a solid color, made by setting both endpoints to the same value
+        let mut data = vec![];
+        for color in [0xF800u16, 0x07E0, 0x001F, 0xFFFF] {
+            data.extend_from_slice(&color.to_be_bytes());
+            data.extend_from_slice(&color.to_be_bytes());
+            data.extend_from_slice(&[0; 4]);
+        }
+
+        let image = decode_texture_data(GVRDataFormat::CMPR, &data, 8, 8, None).unwrap();
+        assert_eq!(image.get_pixel(3, 3), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(4, 3), [0, 0xFF, 0, 0xFF]);
+        assert_eq!(image.get_pixel(3, 4), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(7, 7), [0xFF; 4]);
+    }
+
+    #[test]
+    fn encode_solid_block() {
+        let mut image = RGBAImage::new(8, 8);
+        image
+            .pixels
+            .chunks_exact_mut(4)
+            .for_each(|p| p.copy_from_slice(&[0xFF, 0, 0, 0xFF]));
+
+        for quality in [CMPRQuality::Fast, CMPRQuality::High] {
+            let data = encode_with(&image, quality);
+            assert_eq!(total_error(&image, &data), 0);
+        }
+    }
+
+    #[test]
+    fn encode_transparency() {
+        // Left half transparent, right half opaque white
+        let mut image = RGBAImage::new(8, 8);
+        for y in 0..8 {
+            for x in 4..8 {
+                image.set_pixel(x, y, [0xFF; 4]);
+            }
+        }
+
+        for quality in [CMPRQuality::Fast, CMPRQuality::High] {
+            let data = encode_with(&image, quality);
+
+            // Sub-block 0 is fully transparent, sub-block 1 is fully opaque
+            assert!(data[0..2] <= data[2..4]);
+            assert_eq!(&data[4..8], &[0xFF; 4]);
+            assert_eq!(total_error(&image, &data), 0);
+        }
+    }
+
+    #[test]
+    fn encode_gradient() {
+        let mut image = RGBAImage::new(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                let v = (x * 16 + y) as u8;
+                image.set_pixel(x, y, [v, 0xFF - v, v / 2, 0xFF]);
+            }
+        }
+
+        let fast = encode_with(&image, CMPRQuality::Fast);
+        let high = encode_with(&image, CMPRQuality::High);
+        assert_eq!(fast.len(), high.len());
+
+        let fast_error = total_error(&image, &fast);
+        let high_error = total_error(&image, &high);
+        assert!(high_error <= fast_error);
+
+        // The colors of each block lie on a line, so the error should stay small
+        let pixel_count = 16 * 16 * 3;
+        assert!(high_error / pixel_count < 16, "error: {high_error}");
+    }
+}
@@ -0,0 +1,407 @@
+//! This module contains the validation of GVR textures against the constraints of the GameCube
+//! GX hardware, which the game doesn't check itself. Textures that break them may look wrong,
+//! or crash the game, even though they're perfectly valid GVR files.
+
+use std::fmt;
+
+use super::{GVRDataFormat, GVRTexture};
+
+/// The maximum width and height of a texture that GX can sample from.
+pub const GX_MAX_TEXTURE_SIZE: u16 = 1024;
+
+/// A GX hardware constraint that a texture breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GVRConstraintViolation {
+    /// The texture has no pixels.
+    ZeroDimensions,
+    /// The texture is bigger than [`GX_MAX_TEXTURE_SIZE`] in either dimension.
+    TooLarge {
+        /// The width of the texture.
+        width: u16,
+        /// The height of the texture.
+        height: u16,
+    },
+    /// The dimensions aren't powers of two, which GX only supports with clamped wrapping.
+    NotPowerOfTwo {
+        /// The width of the texture.
+        width: u16,
+        /// The height of the texture.
+        height: u16,
+    },
+    /// The dimensions of a mipmapped texture aren't powers of two, which GX doesn't support at
+    /// all.
+    MipmappedNotPowerOfTwo {
+        /// The width of the texture.
+        width: u16,
+        /// The height of the texture.
+        height: u16,
+    },
+    /// The dimensions aren't a multiple of the block dimensions of the format, so the edge
+    /// blocks are padded.
+    NotBlockAligned {
+        /// The width of a block of the format.
+        block_width: u32,
+        /// The height of a block of the format.
+        block_height: u32,
+    },
+    /// The texture is flagged to have mipmaps, but doesn't have data for the full mip chain the
+    /// header describes. The hardware reads the missing levels from whatever follows the texture.
+    MissingMipLevels {
+        /// The amount of mip levels the header describes.
+        expected: usize,
+        /// The amount of mip levels that are present in the texture data.
+        actual: usize,
+    },
+    /// The texture data is too short to contain the base texture.
+    MissingData {
+        /// The size the texture should at least be, in bytes.
+        expected: usize,
+        /// The actual size of the texture, in bytes.
+        actual: usize,
+    },
+    /// There's data after the last mip level that doesn't make up a full mip level.
+    TrailingData {
+        /// The amount of extra bytes.
+        size: usize,
+    },
+    /// The size of the texture doesn't match the size of its data.
+    SizeMismatch {
+        /// The size stored in [`GVRTexture::size`].
+        size: u32,
+        /// The actual size of [`GVRTexture::data`].
+        actual: usize,
+    },
+    /// The texture is palettized, but isn't flagged to have either an embedded or an external
+    /// palette.
+    MissingPalette,
+    /// The texture isn't palettized, but is flagged to have a palette.
+    UnexpectedPalette,
+    /// The texture is flagged to have both an embedded and an external palette.
+    ConflictingPaletteFlags,
+    /// The texture is flagged to have an embedded palette, but its format can't embed one.
+    EmbeddedPaletteUnsupported,
+    /// The external palette has more entries than the format can index.
+    PaletteTooLarge {
+        /// The amount of entries in the palette.
+        entries: usize,
+        /// The maximum amount of entries the format can index.
+        max: usize,
+    },
+}
+
+impl fmt::Display for GVRConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVRConstraintViolation::ZeroDimensions => write!(f, "texture has no pixels"),
+            GVRConstraintViolation::TooLarge { width, height } => write!(
+                f,
+                "{width}x{height} is bigger than the maximum of \
+                 {GX_MAX_TEXTURE_SIZE}x{GX_MAX_TEXTURE_SIZE}"
+            ),
+            GVRConstraintViolation::NotPowerOfTwo { width, height } => write!(
+                f,
+                "{width}x{height} isn't a power of two, which only works with clamped wrapping"
+            ),
+            GVRConstraintViolation::MipmappedNotPowerOfTwo { width, height } => write!(
+                f,
+                "{width}x{height} isn't a power of two, which doesn't work with mipmaps"
+            ),
+            GVRConstraintViolation::NotBlockAligned {
+                block_width,
+                block_height,
+            } => write!(
+                f,
+                "dimensions aren't a multiple of the {block_width}x{block_height} blocks of the \
+                 format"
+            ),
+            GVRConstraintViolation::MissingMipLevels { expected, actual } => write!(
+                f,
+                "flagged to have {expected} mip levels, but only has {actual}"
+            ),
+            GVRConstraintViolation::MissingData { expected, actual } => write!(
+                f,
+                "texture is {actual} bytes, but the base texture needs {expected} bytes"
+            ),
+            GVRConstraintViolation::TrailingData { size } => write!(
+                f,
+                "{size} bytes after the last mip level don't make up a full mip level"
+            ),
+            GVRConstraintViolation::SizeMismatch { size, actual } => write!(
+                f,
+                "size is {size} bytes, but the texture data is {actual} bytes"
+            ),
+            GVRConstraintViolation::MissingPalette => write!(
+                f,
+                "palettized texture has neither an embedded nor an external palette"
+            ),
+            GVRConstraintViolation::UnexpectedPalette => {
+                write!(
+                    f,
+                    "texture isn't palettized, but is flagged to have a palette"
+                )
+            }
+            GVRConstraintViolation::ConflictingPaletteFlags => write!(
+                f,
+                "texture is flagged to have both an embedded and an external palette"
+            ),
+            GVRConstraintViolation::EmbeddedPaletteUnsupported => {
+                write!(f, "only C4 and C8 textures can embed a palette")
+            }
+            GVRConstraintViolation::PaletteTooLarge { entries, max } => write!(
+                f,
+                "palette has {entries} entries, but the format can only index {max}"
+            ),
+        }
+    }
+}
+
+impl GVRTexture {
+    /// Checks this texture against the constraints of the GX hardware, returning every
+    /// constraint it breaks. An empty list means the texture is fine.
+    pub fn check_constraints(&self) -> Vec<GVRConstraintViolation> {
+        let header = &self.header;
+        let (width, height) = (header.width, header.height);
+        let format = header.data_format;
+        let mut violations = vec![];
+
+        if width == 0 || height == 0 {
+            violations.push(GVRConstraintViolation::ZeroDimensions);
+        } else {
+            if width > GX_MAX_TEXTURE_SIZE || height > GX_MAX_TEXTURE_SIZE {
+                violations.push(GVRConstraintViolation::TooLarge { width, height });
+            }
+
+            if !width.is_power_of_two() || !height.is_power_of_two() {
+                if header.has_mipmaps() {
+                    violations
+                        .push(GVRConstraintViolation::MipmappedNotPowerOfTwo { width, height });
+                } else {
+                    violations.push(GVRConstraintViolation::NotPowerOfTwo { width, height });
+                }
+            }
+
+            let (block_width, block_height) = format.block_dimensions();
+            if u32::from(width) % block_width != 0 || u32::from(height) % block_height != 0 {
+                violations.push(GVRConstraintViolation::NotBlockAligned {
+                    block_width,
+                    block_height,
+                });
+            }
+        }
+
+        let data_len = self.data.get_ref().len();
+        if self.size as usize != data_len {
+            violations.push(GVRConstraintViolation::SizeMismatch {
+                size: self.size,
+                actual: data_len,
+            });
+        }
+
+        let mip_levels = self.mip_levels();
+        match mip_levels.last() {
+            Some(last) => {
+                if header.has_mipmaps() && mip_levels.len() != header.mip_level_count() {
+                    violations.push(GVRConstraintViolation::MissingMipLevels {
+                        expected: header.mip_level_count(),
+                        actual: mip_levels.len(),
+                    });
+                }
+                if data_len > last.range().end {
+                    violations.push(GVRConstraintViolation::TrailingData {
+                        size: data_len - last.range().end,
+                    });
+                }
+            }
+            None => {
+                // Headers without any mip level only have zero dimensions, reported above
+                if let Some(base_level) = header.mip_levels().first() {
+                    violations.push(GVRConstraintViolation::MissingData {
+                        expected: base_level.range().end,
+                        actual: data_len,
+                    });
+                }
+            }
+        }
+
+        let (internal, external) = (header.has_internal_palette(), header.has_external_palette());
+        if format.is_palettized() {
+            if internal && external {
+                violations.push(GVRConstraintViolation::ConflictingPaletteFlags);
+            } else if !internal && !external {
+                violations.push(GVRConstraintViolation::MissingPalette);
+            }
+            if internal && header.internal_palette_entry_count() == 0 {
+                violations.push(GVRConstraintViolation::EmbeddedPaletteUnsupported);
+            }
+
+            let max = match format {
+                GVRDataFormat::C4 => 1 << 4,
+                GVRDataFormat::C8 => 1 << 8,
+                _ => 1 << 14,
+            };
+            if let Some(palette) = &self.external_palette {
+                if palette.entries.len() > max {
+                    violations.push(GVRConstraintViolation::PaletteTooLarge {
+                        entries: palette.entries.len(),
+                        max,
+                    });
+                }
+            }
+        } else if internal || external {
+            violations.push(GVRConstraintViolation::UnexpectedPalette);
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::{
+        encode::{GVREncodeOptions, GVRPalettePlacement},
+        image::RGBAImage,
+        mipmap::GVRMipmaps,
+        palette::GVRPalette,
+        GVRPaletteFormat, GVR_FLAG_EXTERNAL_PALETTE, GVR_FLAG_INTERNAL_PALETTE, GVR_FLAG_MIPMAPS,
+    };
+
+    fn encode(width: u32, height: u32, options: &GVREncodeOptions) -> GVRTexture {
+        GVRTexture::encode("test".into(), &RGBAImage::new(width, height), options).unwrap()
+    }
+
+    #[test]
+    fn valid_textures() {
+        for format in [GVRDataFormat::CMPR, GVRDataFormat::C4, GVRDataFormat::C8] {
+            let options = GVREncodeOptions {
+                mipmaps: GVRMipmaps::Full,
+                ..GVREncodeOptions::new(format)
+            };
+            assert_eq!(encode(64, 32, &options).check_constraints(), vec![]);
+        }
+    }
+
+    #[test]
+    fn dimension_violations() {
+        let options = GVREncodeOptions::new(GVRDataFormat::CMPR);
+        assert_eq!(
+            encode(2048, 12, &options).check_constraints(),
+            vec![
+                GVRConstraintViolation::TooLarge {
+                    width: 2048,
+                    height: 12
+                },
+                GVRConstraintViolation::NotPowerOfTwo {
+                    width: 2048,
+                    height: 12
+                },
+                GVRConstraintViolation::NotBlockAligned {
+                    block_width: 8,
+                    block_height: 8
+                },
+            ]
+        );
+
+        let options = GVREncodeOptions {
+            mipmaps: GVRMipmaps::Full,
+            ..GVREncodeOptions::new(GVRDataFormat::RGB565)
+        };
+        assert_eq!(
+            encode(12, 4, &options).check_constraints(),
+            vec![GVRConstraintViolation::MipmappedNotPowerOfTwo {
+                width: 12,
+                height: 4
+            }]
+        );
+
+        // An edited header that doesn't describe any mip level
+        let mut tex = encode(8, 8, &options);
+        (tex.header.width, tex.header.height) = (0, 0);
+        assert!(tex
+            .check_constraints()
+            .contains(&GVRConstraintViolation::ZeroDimensions));
+    }
+
+    #[test]
+    fn data_violations() {
+        let mut tex = encode(8, 8, &GVREncodeOptions::new(GVRDataFormat::I8));
+        tex.header.flags |= GVR_FLAG_MIPMAPS;
+        tex.data.get_mut().extend_from_slice(&[0; 8]);
+        assert_eq!(
+            tex.check_constraints(),
+            vec![
+                GVRConstraintViolation::SizeMismatch {
+                    size: tex.size,
+                    actual: tex.size as usize + 8
+                },
+                GVRConstraintViolation::MissingMipLevels {
+                    expected: 4,
+                    actual: 1
+                },
+                GVRConstraintViolation::TrailingData { size: 8 },
+            ]
+        );
+
+        // A partial mip chain, with 2 of the 5 levels of a 16x16 texture
+        let options = GVREncodeOptions {
+            mipmaps: GVRMipmaps::Full,
+            ..GVREncodeOptions::new(GVRDataFormat::I8)
+        };
+        let mut partial = encode(16, 16, &options);
+        let end = partial.mip_levels()[1].range().end;
+        partial.data.get_mut().truncate(end);
+        partial.size = end as u32;
+        assert_eq!(
+            partial.check_constraints(),
+            vec![GVRConstraintViolation::MissingMipLevels {
+                expected: 5,
+                actual: 2
+            }]
+        );
+
+        tex.data.get_mut().truncate(0x30);
+        tex.size = 0x30;
+        assert_eq!(
+            tex.check_constraints(),
+            vec![GVRConstraintViolation::MissingData {
+                expected: 0x60,
+                actual: 0x30
+            }]
+        );
+    }
+
+    #[test]
+    fn palette_violations() {
+        let mut tex = encode(8, 8, &GVREncodeOptions::new(GVRDataFormat::RGB5A3));
+        tex.header.flags |= GVR_FLAG_INTERNAL_PALETTE;
+        assert_eq!(
+            tex.check_constraints(),
+            vec![GVRConstraintViolation::UnexpectedPalette]
+        );
+
+        let mut tex = encode(8, 8, &GVREncodeOptions::new(GVRDataFormat::C4));
+        tex.header.flags |= GVR_FLAG_EXTERNAL_PALETTE;
+        tex.external_palette = Some(GVRPalette::new(GVRPaletteFormat::RGB565, vec![0; 17]));
+        assert_eq!(
+            tex.check_constraints(),
+            vec![
+                GVRConstraintViolation::ConflictingPaletteFlags,
+                GVRConstraintViolation::PaletteTooLarge {
+                    entries: 17,
+                    max: 16
+                },
+            ]
+        );
+
+        let options = GVREncodeOptions {
+            palette_placement: GVRPalettePlacement::External,
+            ..GVREncodeOptions::new(GVRDataFormat::C8)
+        };
+        let mut tex = encode(8, 8, &options);
+        tex.header.flags = 0;
+        assert_eq!(
+            tex.check_constraints(),
+            vec![GVRConstraintViolation::MissingPalette]
+        );
+    }
+}
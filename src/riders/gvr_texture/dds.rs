@@ -0,0 +1,902 @@
+//! This module contains the functionality to convert GVR textures to and from DDS files.
+//!
+//! BC1 (DXT1) compressed DDS files map directly onto CMPR textures, so the compressed blocks are
+//! carried across as they are, without decoding and encoding them again. Both the BC1 blocks and
+//! the CMPR sub-blocks are 4x4 pixels, the only differences are the byte order of the color
+//! endpoints, the bit order of the indices and the order the blocks are stored in.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{
+    cmpr::SUB_BLOCK_SIZE,
+    decode::{self, GVRDecodeError},
+    encode::{self, GVREncodeError, GVREncodeOptions},
+    image::RGBAImage,
+    interchange::GVRInterchangeError,
+    mipmap, GVRDataFormat, GVRHeader, GVRTexture, GVR_FLAG_MIPMAPS,
+};
+
+/// The magic at the very start of every DDS file.
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+/// The size of the DDS header, not including the magic.
+const DDS_HEADER_SIZE: u32 = 124;
+/// The size of the pixel format structure in the DDS header.
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+/// The size of the extended DX10 header, which follows the regular header if the FourCC of the
+/// pixel format is "DX10".
+const DX10_HEADER_SIZE: usize = 20;
+
+// Offsets of the DDS header fields, relative to the start of the file
+const HEIGHT_OFFSET: usize = 0x0C;
+const WIDTH_OFFSET: usize = 0x10;
+const DEPTH_OFFSET: usize = 0x18;
+const MIP_MAP_COUNT_OFFSET: usize = 0x1C;
+const PIXEL_FORMAT_FLAGS_OFFSET: usize = 0x50;
+const FOURCC_OFFSET: usize = 0x54;
+const RGB_BIT_COUNT_OFFSET: usize = 0x58;
+const MASKS_OFFSET: usize = 0x5C;
+const CAPS2_OFFSET: usize = 0x70;
+/// The offset of the pixel data (or the DX10 header), right after the header.
+const DATA_OFFSET: usize = 0x80;
+
+// Flags of the DDS header, its pixel format and its capabilities
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x20000;
+
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x200000;
+
+// Values used in the DX10 header
+const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+const DXGI_FORMAT_R8G8B8A8_UNORM_SRGB: u32 = 29;
+const DXGI_FORMAT_BC1_UNORM: u32 = 71;
+const DXGI_FORMAT_BC1_UNORM_SRGB: u32 = 72;
+const DXGI_FORMAT_B8G8R8A8_UNORM: u32 = 87;
+const DXGI_FORMAT_B8G8R8A8_UNORM_SRGB: u32 = 91;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+
+/// The size of a single BC1 block in bytes.
+const BC1_BLOCK_SIZE: usize = 8;
+
+/// The bit masks of the color channels of uncompressed pixels, in R, G, B, A order.
+type ChannelMasks = [u32; 4];
+
+/// The masks used for 32-bit BGRA pixels, which is what uncompressed DDS files are written as.
+const BGRA8_MASKS: ChannelMasks = [0x00FF0000, 0x0000FF00, 0x000000FF, 0xFF000000];
+/// The masks used for 32-bit RGBA pixels.
+const RGBA8_MASKS: ChannelMasks = [0x000000FF, 0x0000FF00, 0x00FF0000, 0xFF000000];
+
+/// The contents of a 2D DDS file, with all of its mip levels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DDSImage {
+    /// BC1 (DXT1) compressed color.
+    BC1 {
+        /// The width of the base level in pixels.
+        width: u32,
+        /// The height of the base level in pixels.
+        height: u32,
+        /// The raw BC1 blocks of every mip level, starting with the base level.
+        levels: Vec<Vec<u8>>,
+    },
+    /// Uncompressed color, with an image for every mip level, starting with the base level.
+    Uncompressed(Vec<RGBAImage>),
+}
+
+impl DDSImage {
+    /// Parses the DDS file in `data`.
+    ///
+    /// BC1 files (including DX10 ones) are kept compressed. Uncompressed files of up to 32 bits
+    /// per pixel are supported in any RGB, luminance or alpha-only layout. Cube maps, volume
+    /// textures and other compressed formats aren't supported.
+    pub fn read(data: &[u8]) -> Result<Self, GVRInterchangeError> {
+        let invalid = |reason: &str| GVRInterchangeError::InvalidDds(reason.to_string());
+
+        if data.len() < DATA_OFFSET {
+            return Err(invalid("file is too short to contain a header"));
+        }
+        if &data[..4] != DDS_MAGIC {
+            return Err(invalid("file doesn't start with \"DDS \""));
+        }
+        if LittleEndian::read_u32(&data[4..]) != DDS_HEADER_SIZE {
+            return Err(invalid("header size is not 124 bytes"));
+        }
+
+        let read_u32 = |offset: usize| LittleEndian::read_u32(&data[offset..]);
+
+        let width = read_u32(WIDTH_OFFSET);
+        let height = read_u32(HEIGHT_OFFSET);
+        if width == 0 || height == 0 {
+            return Err(invalid("image has no pixels"));
+        }
+        if width > u16::MAX.into() || height > u16::MAX.into() {
+            return Err(GVRInterchangeError::InvalidDds(format!(
+                "{width}x{height} is larger than a GVR texture can be"
+            )));
+        }
+
+        if read_u32(CAPS2_OFFSET) & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0
+            || read_u32(DEPTH_OFFSET) > 1
+        {
+            return Err(invalid("cube maps and volume textures are not supported"));
+        }
+
+        // Some writers leave the mip map count flag out, so the count itself is used instead
+        let full_chain = (u32::BITS - width.max(height).leading_zeros()) as usize;
+        let level_count = (read_u32(MIP_MAP_COUNT_OFFSET) as usize).clamp(1, full_chain);
+
+        let pf_flags = read_u32(PIXEL_FORMAT_FLAGS_OFFSET);
+        let fourcc = &data[FOURCC_OFFSET..FOURCC_OFFSET + 4];
+        let mut offset = DATA_OFFSET;
+
+        let layout = if pf_flags & DDPF_FOURCC != 0 {
+            match fourcc {
+                b"DXT1" => PixelLayout::BC1,
+                b"DX10" => {
+                    let dx10 = data
+                        .get(DATA_OFFSET..DATA_OFFSET + DX10_HEADER_SIZE)
+                        .ok_or(invalid("file is too short to contain the DX10 header"))?;
+                    offset += DX10_HEADER_SIZE;
+
+                    let dxgi_format = LittleEndian::read_u32(dx10);
+                    let dimension = LittleEndian::read_u32(&dx10[4..]);
+                    let array_size = LittleEndian::read_u32(&dx10[12..]);
+                    if dimension != D3D10_RESOURCE_DIMENSION_TEXTURE2D || array_size > 1 {
+                        return Err(invalid("only single 2D textures are supported"));
+                    }
+
+                    match dxgi_format {
+                        DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB => PixelLayout::BC1,
+                        DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => {
+                            PixelLayout::Masked {
+                                bit_count: 32,
+                                masks: RGBA8_MASKS,
+                                luminance: false,
+                            }
+                        }
+                        DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => {
+                            PixelLayout::Masked {
+                                bit_count: 32,
+                                masks: BGRA8_MASKS,
+                                luminance: false,
+                            }
+                        }
+                        _ => {
+                            return Err(GVRInterchangeError::InvalidDds(format!(
+                                "DXGI format {dxgi_format} is not supported"
+                            )))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(GVRInterchangeError::InvalidDds(format!(
+                        "compressed format \"{}\" is not supported",
+                        String::from_utf8_lossy(fourcc)
+                    )))
+                }
+            }
+        } else if pf_flags & (DDPF_RGB | DDPF_LUMINANCE | DDPF_ALPHA) != 0 {
+            let bit_count = read_u32(RGB_BIT_COUNT_OFFSET);
+            if !matches!(bit_count, 8 | 16 | 24 | 32) {
+                return Err(GVRInterchangeError::InvalidDds(format!(
+                    "{bit_count} bits per pixel is not supported"
+                )));
+            }
+
+            let mut masks = [0; 4];
+            for (i, mask) in masks.iter_mut().enumerate() {
+                *mask = read_u32(MASKS_OFFSET + i * 4);
+            }
+            if pf_flags & (DDPF_ALPHAPIXELS | DDPF_ALPHA) == 0 {
+                masks[3] = 0;
+            }
+
+            PixelLayout::Masked {
+                bit_count,
+                masks,
+                luminance: pf_flags & DDPF_LUMINANCE != 0,
+            }
+        } else {
+            return Err(invalid("unknown pixel format"));
+        };
+
+        let mut bc1_levels = vec![];
+        let mut images = vec![];
+        for level in 0..level_count {
+            let level_width = (width >> level).max(1);
+            let level_height = (height >> level).max(1);
+            let too_short = || {
+                GVRInterchangeError::InvalidDds(format!(
+                    "file is too short to contain mip level {level}"
+                ))
+            };
+
+            let end = layout
+                .level_size(level_width, level_height)
+                .and_then(|size| offset.checked_add(size))
+                .ok_or_else(too_short)?;
+            let level_data = data.get(offset..end).ok_or_else(too_short)?;
+            offset = end;
+
+            match layout {
+                PixelLayout::BC1 => bc1_levels.push(level_data.to_vec()),
+                PixelLayout::Masked {
+                    bit_count,
+                    masks,
+                    luminance,
+                } => images.push(read_masked_pixels(
+                    level_data,
+                    level_width,
+                    level_height,
+                    bit_count,
+                    masks,
+                    luminance,
+                )),
+            }
+        }
+
+        match layout {
+            PixelLayout::BC1 => Ok(DDSImage::BC1 {
+                width,
+                height,
+                levels: bc1_levels,
+            }),
+            PixelLayout::Masked { .. } => Ok(DDSImage::Uncompressed(images)),
+        }
+    }
+
+    /// Serializes this image into a DDS file. BC1 images are written with the "DXT1" FourCC, and
+    /// uncompressed images are written as 32-bit BGRA.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (width, height) = self.dimensions();
+        let level_count = self.level_count() as u32;
+
+        let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+        let mut caps = DDSCAPS_TEXTURE;
+        if level_count > 1 {
+            flags |= DDSD_MIPMAPCOUNT;
+            caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+        }
+
+        let (pitch_or_linear_size, pf_flags, fourcc, bit_count, masks) = match self {
+            DDSImage::BC1 { levels, .. } => {
+                flags |= DDSD_LINEARSIZE;
+                let linear_size = levels.first().map_or(0, Vec::len);
+                (linear_size as u32, DDPF_FOURCC, *b"DXT1", 0, [0; 4])
+            }
+            DDSImage::Uncompressed(_) => {
+                flags |= DDSD_PITCH;
+                (
+                    width * 4,
+                    DDPF_RGB | DDPF_ALPHAPIXELS,
+                    [0; 4],
+                    32,
+                    BGRA8_MASKS,
+                )
+            }
+        };
+
+        let mut header = [0u32; DDS_HEADER_SIZE as usize / 4];
+        header[0] = DDS_HEADER_SIZE;
+        header[1] = flags;
+        header[2] = height;
+        header[3] = width;
+        header[4] = pitch_or_linear_size;
+        header[6] = level_count;
+        header[18] = DDS_PIXEL_FORMAT_SIZE;
+        header[19] = pf_flags;
+        header[20] = u32::from_le_bytes(fourcc);
+        header[21] = bit_count;
+        header[22..26].copy_from_slice(&masks);
+        header[26] = caps;
+
+        let mut buf = DDS_MAGIC.to_vec();
+        buf.extend(header.iter().flat_map(|v| v.to_le_bytes()));
+
+        match self {
+            DDSImage::BC1 { levels, .. } => {
+                for level in levels {
+                    buf.extend_from_slice(level);
+                }
+            }
+            DDSImage::Uncompressed(images) => {
+                for image in images {
+                    buf.extend(
+                        image
+                            .pixels
+                            .chunks_exact(4)
+                            .flat_map(|p| [p[2], p[1], p[0], p[3]]),
+                    );
+                }
+            }
+        }
+
+        buf
+    }
+
+    /// Returns the dimensions of the base level.
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            DDSImage::BC1 { width, height, .. } => (*width, *height),
+            DDSImage::Uncompressed(images) => images
+                .first()
+                .map(|image| (image.width, image.height))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the amount of mip levels, including the base level.
+    pub fn level_count(&self) -> usize {
+        match self {
+            DDSImage::BC1 { levels, .. } => levels.len(),
+            DDSImage::Uncompressed(images) => images.len(),
+        }
+    }
+
+    /// Decodes every mip level of this image into an [`RGBAImage`].
+    ///
+    /// BC1 blocks are decoded the way the GameCube hardware decodes CMPR blocks, so the colors
+    /// between the endpoints may differ very slightly from what a PC would show.
+    pub fn decode(&self) -> Vec<RGBAImage> {
+        match self {
+            DDSImage::BC1 {
+                width,
+                height,
+                levels,
+            } => levels
+                .iter()
+                .enumerate()
+                .map(|(i, level)| {
+                    let level_width = (width >> i).max(1);
+                    let level_height = (height >> i).max(1);
+                    let cmpr_data = bc1_to_cmpr(level, level_width, level_height);
+
+                    // The CMPR data always has the right size, so decoding can't fail
+                    decode::decode_texture_data(
+                        GVRDataFormat::CMPR,
+                        &cmpr_data,
+                        level_width,
+                        level_height,
+                        None,
+                    )
+                    .unwrap()
+                })
+                .collect(),
+            DDSImage::Uncompressed(images) => images.clone(),
+        }
+    }
+}
+
+/// How the pixels of a DDS file are stored.
+#[derive(Clone, Copy)]
+enum PixelLayout {
+    BC1,
+    Masked {
+        bit_count: u32,
+        masks: ChannelMasks,
+        luminance: bool,
+    },
+}
+
+impl PixelLayout {
+    /// Returns the size in bytes of a mip level with the given dimensions, or [`None`] if it
+    /// doesn't fit in a `usize`.
+    fn level_size(&self, width: u32, height: u32) -> Option<usize> {
+        match self {
+            PixelLayout::BC1 => (width.div_ceil(4) as usize)
+                .checked_mul(height.div_ceil(4) as usize)?
+                .checked_mul(BC1_BLOCK_SIZE),
+            PixelLayout::Masked { bit_count, .. } => (width as usize)
+                .checked_mul(*bit_count as usize)?
+                .div_ceil(8)
+                .checked_mul(height as usize),
+        }
+    }
+}
+
+/// Reads uncompressed little-endian pixels, extracting each channel with its mask in `masks`.
+/// Channels without a mask are 0, except for alpha, which is fully opaque.
+fn read_masked_pixels(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    bit_count: u32,
+    masks: ChannelMasks,
+    luminance: bool,
+) -> RGBAImage {
+    let bytes_per_pixel = bit_count as usize / 8;
+    let mut image = RGBAImage::new(width, height);
+
+    for (out, pixel) in image
+        .pixels
+        .chunks_exact_mut(4)
+        .zip(data.chunks_exact(bytes_per_pixel))
+    {
+        let value = LittleEndian::read_uint(pixel, bytes_per_pixel) as u32;
+        let channel = |mask: u32| extract_channel(value, mask);
+
+        let [r, g, b, a] = masks.map(channel);
+        out[0] = r.unwrap_or_default();
+        out[1] = if luminance {
+            out[0]
+        } else {
+            g.unwrap_or_default()
+        };
+        out[2] = if luminance {
+            out[0]
+        } else {
+            b.unwrap_or_default()
+        };
+        out[3] = a.unwrap_or(0xFF);
+    }
+
+    image
+}
+
+/// Extracts the channel given by `mask` from `value`, scaled to 8 bits. Returns [`None`] if
+/// there's no such channel.
+fn extract_channel(value: u32, mask: u32) -> Option<u8> {
+    if mask == 0 {
+        return None;
+    }
+
+    let shift = mask.trailing_zeros();
+    let max = (mask >> shift) as u64;
+    let channel = ((value & mask) >> shift) as u64;
+
+    Some(((channel * 255 + max / 2) / max) as u8)
+}
+
+/// Converts a single 8 byte BC1 block into a CMPR sub-block, or the other way around, as the
+/// conversion is the same in both directions.
+fn swap_block_order(block: &[u8], out: &mut [u8]) {
+    // Swap the byte order of both endpoints
+    out[0] = block[1];
+    out[1] = block[0];
+    out[2] = block[3];
+    out[3] = block[2];
+
+    // Reverse the order of the 2-bit indices in each row
+    for (out_row, &row) in out[4..8].iter_mut().zip(&block[4..8]) {
+        *out_row = ((row & 0x03) << 6) | ((row & 0x0C) << 2) | ((row & 0x30) >> 2) | (row >> 6);
+    }
+}
+
+/// Calls `f` with the index of every BC1 block of a texture with the given dimensions and the
+/// offset of the matching CMPR sub-block. BC1 blocks are stored in row-major order, while CMPR
+/// groups them by 2x2 into 8x8 blocks.
+fn for_each_sub_block(width: u32, height: u32, mut f: impl FnMut(usize, usize)) {
+    let blocks_x = width.div_ceil(4) as usize;
+    let blocks_y = height.div_ceil(4) as usize;
+    let cmpr_blocks_x = width.div_ceil(8) as usize;
+
+    for y in 0..blocks_y {
+        for x in 0..blocks_x {
+            let cmpr_block = (y / 2) * cmpr_blocks_x + x / 2;
+            let sub_block = (y % 2) * 2 + x % 2;
+
+            f(
+                y * blocks_x + x,
+                (cmpr_block * 4 + sub_block) * SUB_BLOCK_SIZE,
+            );
+        }
+    }
+}
+
+/// Converts the BC1 blocks of a single mip level with the given dimensions into CMPR texture
+/// data. Sub-blocks that lie fully outside of the texture are left zeroed.
+pub fn bc1_to_cmpr(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut cmpr_data = vec![0; GVRDataFormat::CMPR.data_size(width, height)];
+
+    for_each_sub_block(width, height, |bc1_idx, cmpr_offset| {
+        let bc1_offset = bc1_idx * BC1_BLOCK_SIZE;
+        if let Some(block) = data.get(bc1_offset..bc1_offset + BC1_BLOCK_SIZE) {
+            swap_block_order(
+                block,
+                &mut cmpr_data[cmpr_offset..cmpr_offset + SUB_BLOCK_SIZE],
+            );
+        }
+    });
+
+    cmpr_data
+}
+
+/// Converts the CMPR texture data of a single mip level with the given dimensions into BC1
+/// blocks. Sub-blocks that lie fully outside of the texture are left out.
+pub fn cmpr_to_bc1(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let block_count = (width.div_ceil(4) * height.div_ceil(4)) as usize;
+    let mut bc1_data = vec![0; block_count * BC1_BLOCK_SIZE];
+
+    for_each_sub_block(width, height, |bc1_idx, cmpr_offset| {
+        let bc1_offset = bc1_idx * BC1_BLOCK_SIZE;
+        if let Some(sub_block) = data.get(cmpr_offset..cmpr_offset + SUB_BLOCK_SIZE) {
+            swap_block_order(
+                sub_block,
+                &mut bc1_data[bc1_offset..bc1_offset + BC1_BLOCK_SIZE],
+            );
+        }
+    });
+
+    bc1_data
+}
+
+impl GVRTexture {
+    /// Converts the DDS file in `data` into a new GVR texture named `name`, as per the given
+    /// `options`.
+    ///
+    /// If the DDS file is BC1 compressed and [`GVREncodeOptions::format`] is CMPR, the blocks are
+    /// carried across without being encoded again, making the conversion lossless. Otherwise the
+    /// DDS file is decoded and encoded into the requested format.
+    ///
+    /// Mip levels stored in the DDS file are carried across as well. Only if the DDS file has no
+    /// mip levels of its own, they're generated as per [`GVREncodeOptions::mipmaps`]. If it has
+    /// some, but not the full mip chain, the missing levels are generated down to 1x1.
+    pub fn from_dds(
+        name: String,
+        data: &[u8],
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVRInterchangeError> {
+        let dds = DDSImage::read(data)?;
+
+        match &dds {
+            DDSImage::BC1 {
+                width,
+                height,
+                levels,
+            } if options.format == GVRDataFormat::CMPR => {
+                GVRTexture::from_bc1(name, *width, *height, levels, options)
+            }
+            _ => {
+                let images = dds.decode();
+                if images.len() > 1 {
+                    Ok(GVRTexture::encode_mip_levels(name, &images, options)?)
+                } else {
+                    Ok(GVRTexture::encode(name, &images[0], options)?)
+                }
+            }
+        }
+    }
+
+    /// Builds a new CMPR texture out of the BC1 blocks of every mip level in `levels`, generating
+    /// the mip levels as per `options` if there's only the base level. A partial mip chain is
+    /// completed from its smallest level.
+    fn from_bc1(
+        name: String,
+        width: u32,
+        height: u32,
+        levels: &[Vec<u8>],
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVRInterchangeError> {
+        if width > u16::MAX.into() || height > u16::MAX.into() {
+            return Err(GVREncodeError::InvalidDimensions { width, height }.into());
+        }
+
+        let mut body = vec![];
+        let mut smallest_start = 0;
+        for (i, level) in levels.iter().enumerate() {
+            smallest_start = body.len();
+            body.extend(bc1_to_cmpr(
+                level,
+                (width >> i).max(1),
+                (height >> i).max(1),
+            ));
+        }
+
+        // The header always describes the full mip chain, so a partial one is completed
+        let level_count = if levels.len() == 1 {
+            options.mipmaps.level_count(width, height)
+        } else {
+            mipmap::full_level_count(width, height)
+        };
+
+        if level_count > levels.len() {
+            let smallest_level = levels.len() - 1;
+            let smallest = decode::decode_texture_data(
+                GVRDataFormat::CMPR,
+                &body[smallest_start..],
+                (width >> smallest_level).max(1),
+                (height >> smallest_level).max(1),
+                None,
+            )?;
+            let generated = mipmap::generate_mip_levels(
+                &smallest,
+                level_count - smallest_level,
+                options.mipmap_filter,
+                options.gamma_correct_mipmaps,
+            );
+
+            for level in &generated[1..] {
+                body.extend(encode::encode_texture_data(level, options)?);
+            }
+        }
+
+        let mut header = GVRHeader {
+            global_index: options.global_index,
+            data_format: GVRDataFormat::CMPR,
+            width: width as u16,
+            height: height as u16,
+            ..Default::default()
+        };
+        if level_count > 1 {
+            header.flags |= GVR_FLAG_MIPMAPS;
+        }
+
+        Ok(GVRTexture::from_header_and_body(name, header, &body))
+    }
+
+    /// Converts this [`GVRTexture`] into a DDS file, including all of its mip levels.
+    ///
+    /// CMPR textures are written as BC1 without being decoded, making the conversion lossless.
+    /// Every other format is decoded and written as uncompressed 32-bit BGRA.
+    pub fn to_dds(&self) -> Result<Vec<u8>, GVRInterchangeError> {
+        let mip_levels = self.mip_levels();
+        if mip_levels.is_empty() {
+            let expected = self
+                .header
+                .mip_levels()
+                .first()
+                .map_or(0, |l| l.range().end);
+            return Err(GVRDecodeError::NotEnoughData {
+                expected,
+                found: self.data.get_ref().len(),
+            }
+            .into());
+        }
+
+        let dds = if self.header.data_format == GVRDataFormat::CMPR {
+            let levels = mip_levels
+                .iter()
+                .map(|level| {
+                    let data = &self.data.get_ref()[level.range()];
+                    cmpr_to_bc1(data, level.width, level.height)
+                })
+                .collect();
+
+            DDSImage::BC1 {
+                width: self.header.width.into(),
+                height: self.header.height.into(),
+                levels,
+            }
+        } else {
+            let images = (0..mip_levels.len().max(1))
+                .map(|level| self.decode_mip_level(level))
+                .collect::<Result<_, _>>()?;
+
+            DDSImage::Uncompressed(images)
+        };
+
+        Ok(dds.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::mipmap::GVRMipmaps;
+
+    /// Makes BC1 data with a different block at every position, for a full mip chain of a
+    /// texture with the given dimensions.
+    fn make_bc1_levels(width: u32, height: u32) -> Vec<Vec<u8>> {
+        let level_count = (u32::BITS - width.max(height).leading_zeros()) as usize;
+
+        (0..level_count)
+            .map(|i| {
+                let blocks =
+                    ((width >> i).max(1).div_ceil(4) * (height >> i).max(1).div_ceil(4)) as usize;
+                (0..blocks * BC1_BLOCK_SIZE)
+                    .map(|j| (j * 7 + i * 13) as u8)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn make_image(width: u32, height: u32) -> RGBAImage {
+        let mut image = RGBAImage::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set_pixel(x, y, [(x * 16) as u8, (y * 16) as u8, 0x80, (x * y) as u8]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn bc1_block_conversion() {
+        let bc1 = [0x00, 0xF8, 0x1F, 0x00, 0b00_01_10_11, 0, 0xFF, 0];
+        let cmpr = bc1_to_cmpr(&bc1, 4, 4);
+
+        assert_eq!(
+            &cmpr[..8],
+            &[0xF8, 0x00, 0x00, 0x1F, 0b11_10_01_00, 0, 0xFF, 0]
+        );
+        assert!(cmpr[8..].iter().all(|&b| b == 0));
+        assert_eq!(cmpr_to_bc1(&cmpr, 4, 4), bc1);
+    }
+
+    #[test]
+    fn bc1_block_order() {
+        let levels = make_bc1_levels(16, 8);
+        let cmpr = bc1_to_cmpr(&levels[0], 16, 8);
+
+        // The second BC1 block of the first row is the top-right sub-block of the first CMPR block
+        let mut expected = [0; 8];
+        swap_block_order(&levels[0][8..16], &mut expected);
+        assert_eq!(&cmpr[8..16], &expected);
+
+        // The first BC1 block of the second row is the bottom-left sub-block
+        swap_block_order(&levels[0][32..40], &mut expected);
+        assert_eq!(&cmpr[16..24], &expected);
+
+        assert_eq!(cmpr_to_bc1(&cmpr, 16, 8), levels[0]);
+    }
+
+    #[test]
+    fn bc1_dds_to_cmpr_is_lossless() {
+        let dds = DDSImage::BC1 {
+            width: 16,
+            height: 8,
+            levels: make_bc1_levels(16, 8),
+        };
+        let dds_bytes = dds.to_bytes();
+        let options = GVREncodeOptions::new(GVRDataFormat::CMPR);
+
+        let tex = GVRTexture::from_dds("test".into(), &dds_bytes, &options).unwrap();
+        assert_eq!(tex.header.data_format, GVRDataFormat::CMPR);
+        assert_eq!(tex.mip_levels().len(), 5);
+        assert_eq!(tex.size as usize, tex.data.get_ref().len());
+
+        assert_eq!(DDSImage::read(&dds_bytes).unwrap(), dds);
+        assert_eq!(tex.to_dds().unwrap(), dds_bytes);
+    }
+
+    #[test]
+    fn bc1_dds_generates_missing_mipmaps() {
+        let mut levels = make_bc1_levels(8, 8);
+        levels.truncate(1);
+        let dds_bytes = DDSImage::BC1 {
+            width: 8,
+            height: 8,
+            levels,
+        }
+        .to_bytes();
+
+        let mut options = GVREncodeOptions::new(GVRDataFormat::CMPR);
+        options.mipmaps = GVRMipmaps::Full;
+
+        let tex = GVRTexture::from_dds("test".into(), &dds_bytes, &options).unwrap();
+        assert!(tex.header.has_mipmaps());
+        assert_eq!(tex.mip_levels().len(), 4);
+    }
+
+    #[test]
+    fn bc1_dds_completes_partial_mip_chain() {
+        let mut levels = make_bc1_levels(16, 16);
+        levels.truncate(2);
+        let dds_bytes = DDSImage::BC1 {
+            width: 16,
+            height: 16,
+            levels: levels.clone(),
+        }
+        .to_bytes();
+
+        let options = GVREncodeOptions::new(GVRDataFormat::CMPR);
+        let tex = GVRTexture::from_dds("test".into(), &dds_bytes, &options).unwrap();
+        assert!(tex.header.has_mipmaps());
+        assert_eq!(tex.mip_levels().len(), tex.header.mip_level_count());
+        assert_eq!(tex.mip_levels().len(), 5);
+        assert_eq!(tex.size as usize, tex.mip_levels()[4].range().end);
+
+        // The levels from the DDS file are still carried across as is
+        let second = &tex.mip_levels()[1];
+        assert_eq!(
+            tex.data.get_ref()[second.range()],
+            bc1_to_cmpr(&levels[1], 8, 8)[..]
+        );
+    }
+
+    #[test]
+    fn uncompressed_dds_round_trip() {
+        let levels =
+            mipmap::generate_mip_levels(&make_image(8, 4), 4, mipmap::GVRMipmapFilter::Box, false);
+        let dds_bytes = DDSImage::Uncompressed(levels.clone()).to_bytes();
+        let options = GVREncodeOptions::new(GVRDataFormat::ARGB8);
+
+        let tex = GVRTexture::from_dds("test".into(), &dds_bytes, &options).unwrap();
+        assert_eq!(tex.mip_levels().len(), 4);
+        for (i, level) in levels.iter().enumerate() {
+            assert_eq!(&tex.decode_mip_level(i).unwrap(), level);
+        }
+
+        assert_eq!(tex.to_dds().unwrap(), dds_bytes);
+    }
+
+    #[test]
+    fn truncated_cmpr_texture_to_dds() {
+        let header = GVRHeader {
+            data_format: GVRDataFormat::CMPR,
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        let tex = GVRTexture::from_header_and_body(String::new(), header, &[0; 8]);
+        assert!(tex.mip_levels().is_empty());
+
+        assert!(matches!(
+            tex.to_dds(),
+            Err(GVRInterchangeError::Decode(GVRDecodeError::NotEnoughData {
+                expected: 0x820,
+                found: 0x28
+            }))
+        ));
+
+        let dds = DDSImage::BC1 {
+            width: 64,
+            height: 64,
+            levels: vec![],
+        };
+        assert_eq!(dds.to_bytes().len(), 4 + DDS_HEADER_SIZE as usize);
+    }
+
+    #[test]
+    fn read_masked_dds() {
+        // A 2x1 24-bit BGR file, the way most legacy writers store it
+        let mut header = [0u32; 31];
+        header[0] = DDS_HEADER_SIZE;
+        header[2] = 1;
+        header[3] = 2;
+        header[18] = DDS_PIXEL_FORMAT_SIZE;
+        header[19] = DDPF_RGB;
+        header[21] = 24;
+        header[22..25].copy_from_slice(&[0xFF0000, 0x00FF00, 0x0000FF]);
+
+        let mut buf = DDS_MAGIC.to_vec();
+        buf.extend(header.iter().flat_map(|v| v.to_le_bytes()));
+        buf.extend_from_slice(&[0x10, 0x20, 0x30, 0xFF, 0x00, 0x80]);
+
+        let DDSImage::Uncompressed(images) = DDSImage::read(&buf).unwrap() else {
+            panic!("expected an uncompressed image");
+        };
+        assert_eq!(
+            images[0].pixels,
+            vec![0x30, 0x20, 0x10, 0xFF, 0x80, 0x00, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn invalid_dds() {
+        let mut buf = DDSImage::Uncompressed(vec![make_image(4, 4)]).to_bytes();
+        buf.truncate(buf.len() - 1);
+        assert!(matches!(
+            DDSImage::read(&buf),
+            Err(GVRInterchangeError::InvalidDds(_))
+        ));
+
+        buf[0] = b'X';
+        assert!(matches!(
+            DDSImage::read(&buf),
+            Err(GVRInterchangeError::InvalidDds(_))
+        ));
+
+        // Dimensions that would overflow the size of the image data
+        let mut buf = DDSImage::Uncompressed(vec![make_image(4, 4)]).to_bytes();
+        buf[WIDTH_OFFSET..WIDTH_OFFSET + 4].copy_from_slice(&0x4000_0000u32.to_le_bytes());
+        assert!(matches!(
+            DDSImage::read(&buf),
+            Err(GVRInterchangeError::InvalidDds(_))
+        ));
+    }
+}
@@ -0,0 +1,585 @@
+//! This module contains the functionality to decode the tiled GameCube texture data of GVR
+//! textures into plain RGBA8 images.
+
+use std::fmt;
+
+use super::{cmpr, image::RGBAImage, palette::GVRPalette, GVRDataFormat};
+
+/// An error that occurred while decoding GVR texture data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GVRDecodeError {
+    /// Decoding the given format isn't supported.
+    UnsupportedFormat(GVRDataFormat),
+    /// The texture is palettized, but no palette was given.
+    MissingPalette,
+    /// The requested mip level doesn't exist in the texture.
+    MipLevelOutOfRange {
+        /// The requested mip level.
+        level: usize,
+        /// The amount of mip levels in the texture.
+        count: usize,
+    },
+    /// The texture data is shorter than what the format and dimensions require.
+    NotEnoughData {
+        /// The amount of bytes required to decode the texture.
+        expected: usize,
+        /// The amount of bytes that were available.
+        found: usize,
+    },
+    /// The given data is bigger than the part of the texture it's meant to replace.
+    SizeMismatch {
+        /// The size in bytes of the replaced part of the texture.
+        expected: usize,
+        /// The size in bytes of the given data.
+        found: usize,
+    },
+}
+
+impl fmt::Display for GVRDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVRDecodeError::UnsupportedFormat(format) => {
+                write!(f, "decoding {format} textures is not supported")
+            }
+            GVRDecodeError::MissingPalette => {
+                write!(
+                    f,
+                    "texture is palettized, but has no palette to decode with"
+                )
+            }
+            GVRDecodeError::MipLevelOutOfRange { level, count } => write!(
+                f,
+                "mip level {level} doesn't exist, the texture only has {count} mip level(s)"
+            ),
+            GVRDecodeError::NotEnoughData { expected, found } => write!(
+                f,
+                "texture data is too short, expected {expected:#x} bytes but found {found:#x}"
+            ),
+            GVRDecodeError::SizeMismatch { expected, found } => write!(
+                f,
+                "texture data is too long, expected {expected:#x} bytes but found {found:#x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GVRDecodeError {}
+
+/// Decodes the tiled texture `data` of the given `format` into an [`RGBAImage`] with the given
+/// dimensions.
+///
+/// `data` has to start at the first block of the texture. Any data after the texture is ignored.
+///
+/// Palettized formats require a `palette` to be given, while it's ignored for every other format.
+/// Palette indices that are outside of the palette are decoded as fully transparent black.
+pub fn decode_texture_data(
+    format: GVRDataFormat,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    palette: Option<&GVRPalette>,
+) -> Result<RGBAImage, GVRDecodeError> {
+    let expected = format.data_size(width, height);
+    if data.len() < expected {
+        return Err(GVRDecodeError::NotEnoughData {
+            expected,
+            found: data.len(),
+        });
+    }
+
+    let data = &data[..expected];
+
+    if format.is_palettized() {
+        let colors = palette.ok_or(GVRDecodeError::MissingPalette)?.colors();
+        let decode_block = |block: &[u8], out: &mut [[u8; 4]]| {
+            decode_indexed_block(format, block, &colors, out);
+        };
+
+        return Ok(decode_tiled(format, data, width, height, decode_block));
+    }
+
+    let decode_block: fn(&[u8], &mut [[u8; 4]]) = match format {
+        GVRDataFormat::I4 => decode_i4_block,
+        GVRDataFormat::I8 => decode_i8_block,
+        GVRDataFormat::IA4 => decode_ia4_block,
+        GVRDataFormat::IA8 => decode_ia8_block,
+        GVRDataFormat::RGB565 => decode_rgb565_block,
+        GVRDataFormat::RGB5A3 => decode_rgb5a3_block,
+        GVRDataFormat::ARGB8 => decode_argb8_block,
+        GVRDataFormat::CMPR => cmpr::decode_cmpr_block,
+        _ => return Err(GVRDecodeError::UnsupportedFormat(format)),
+    };
+
+    Ok(decode_tiled(format, data, width, height, decode_block))
+}
+
+/// The raw value of a single texel, as it's stored in the texture data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GVRTexel {
+    /// The value of a texel in any format other than CMPR, taking up as many bits as a pixel of
+    /// the format does. For palettized formats, this is the palette index, without the unused
+    /// upper bits of C14X2. ARGB8 texels are put back together from the two halves of their block.
+    Value(u32),
+    /// A texel of a CMPR texture, which is made up of the two endpoint colors of its 4x4
+    /// sub-block and its 2-bit index into the colors of the sub-block.
+    Cmpr {
+        /// The first endpoint color of the sub-block, in RGB565.
+        color0: u16,
+        /// The second endpoint color of the sub-block, in RGB565.
+        color1: u16,
+        /// The index of the texel into the colors of the sub-block.
+        index: u8,
+    },
+}
+
+/// Reads the raw value of the texel at `x` and `y` from the tiled texture `data` of the given
+/// `format` and `width`.
+///
+/// `data` has to start at the first block of the texture. Returns [`None`] if `x` is outside of
+/// the texture, or if `data` is too short to contain the texel.
+pub fn read_texel(
+    format: GVRDataFormat,
+    data: &[u8],
+    width: u32,
+    x: u32,
+    y: u32,
+) -> Option<GVRTexel> {
+    if x >= width {
+        return None;
+    }
+
+    let (block_width, block_height) = format.block_dimensions();
+    let block_idx = (y / block_height) * width.div_ceil(block_width) + x / block_width;
+    let block = data
+        .get(block_idx as usize * format.block_size()..)?
+        .get(..format.block_size())?;
+
+    let (x, y) = ((x % block_width) as usize, (y % block_height) as usize);
+    let i = y * block_width as usize + x;
+
+    let texel = match format {
+        GVRDataFormat::CMPR => {
+            // 4x4 sub-blocks are stored left to right and top to bottom
+            let sub_block = &block[((y / 4) * 2 + x / 4) * 8..][..8];
+            let row = sub_block[4 + y % 4];
+
+            GVRTexel::Cmpr {
+                color0: u16::from_be_bytes([sub_block[0], sub_block[1]]),
+                color1: u16::from_be_bytes([sub_block[2], sub_block[3]]),
+                index: (row >> (6 - (x % 4) * 2)) & 0x3,
+            }
+        }
+        GVRDataFormat::ARGB8 => GVRTexel::Value(u32::from_be_bytes([
+            block[i * 2],
+            block[i * 2 + 1],
+            block[32 + i * 2],
+            block[32 + i * 2 + 1],
+        ])),
+        _ => match format.bits_per_pixel() {
+            // The first pixel is stored in the upper nibble
+            4 => GVRTexel::Value(u32::from(block[i / 2] >> (4 - (i % 2) * 4) & 0xF)),
+            8 => GVRTexel::Value(block[i].into()),
+            _ => {
+                let value = u16::from_be_bytes([block[i * 2], block[i * 2 + 1]]);
+                if format == GVRDataFormat::C14X2 {
+                    GVRTexel::Value((value & 0x3FFF).into())
+                } else {
+                    GVRTexel::Value(value.into())
+                }
+            }
+        },
+    };
+
+    Some(texel)
+}
+
+/// Walks through all the blocks of the tiled texture `data`, decoding each one with
+/// `decode_block` and placing the resulting pixels into the image.
+///
+/// `decode_block` receives the bytes of a single block, and has to write the colors of the block
+/// in row-major order into the given output slice.
+fn decode_tiled(
+    format: GVRDataFormat,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    decode_block: impl Fn(&[u8], &mut [[u8; 4]]),
+) -> RGBAImage {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format.block_size();
+    let blocks_x = width.div_ceil(block_width);
+
+    let mut image = RGBAImage::new(width, height);
+    let mut block_pixels = vec![[0; 4]; (block_width * block_height) as usize];
+
+    for (block_idx, block) in data.chunks_exact(block_size).enumerate() {
+        let block_x = (block_idx as u32 % blocks_x) * block_width;
+        let block_y = (block_idx as u32 / blocks_x) * block_height;
+
+        decode_block(block, &mut block_pixels);
+
+        for (i, color) in block_pixels.iter().enumerate() {
+            let x = block_x + i as u32 % block_width;
+            let y = block_y + i as u32 / block_width;
+
+            // Blocks on the edges may reach past the actual texture dimensions
+            if x < width && y < height {
+                image.set_pixel(x, y, *color);
+            }
+        }
+    }
+
+    image
+}
+
+/// Intensity formats are decoded the same way the hardware does, the intensity is replicated into
+/// all 4 channels, including alpha.
+fn decode_i4_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (i, color) in out.iter_mut().enumerate() {
+        // The first pixel is stored in the upper nibble
+        let byte = block[i / 2];
+        let value = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+        *color = [expand4(value.into()); 4];
+    }
+}
+
+fn decode_i8_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (color, &value) in out.iter_mut().zip(block) {
+        *color = [value; 4];
+    }
+}
+
+/// IA4 stores the alpha in the upper nibble and the intensity in the lower nibble.
+fn decode_ia4_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (color, &value) in out.iter_mut().zip(block) {
+        let intensity = expand4((value & 0xF).into());
+        *color = [
+            intensity,
+            intensity,
+            intensity,
+            expand4((value >> 4).into()),
+        ];
+    }
+}
+
+/// IA8 stores the alpha in the first byte and the intensity in the second byte.
+fn decode_ia8_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (color, texel) in out.iter_mut().zip(block.chunks_exact(2)) {
+        *color = ia8_to_rgba(u16::from_be_bytes([texel[0], texel[1]]));
+    }
+}
+
+fn decode_rgb565_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (color, texel) in out.iter_mut().zip(block.chunks_exact(2)) {
+        *color = rgb565_to_rgba(u16::from_be_bytes([texel[0], texel[1]]));
+    }
+}
+
+fn decode_rgb5a3_block(block: &[u8], out: &mut [[u8; 4]]) {
+    for (color, texel) in out.iter_mut().zip(block.chunks_exact(2)) {
+        *color = rgb5a3_to_rgba(u16::from_be_bytes([texel[0], texel[1]]));
+    }
+}
+
+/// ARGB8 blocks are stored in two passes: first the alpha and red values of all 16 pixels,
+/// then the green and blue values.
+fn decode_argb8_block(block: &[u8], out: &mut [[u8; 4]]) {
+    let (ar, gb) = block.split_at(32);
+
+    for (i, color) in out.iter_mut().enumerate() {
+        *color = [ar[i * 2 + 1], gb[i * 2], gb[i * 2 + 1], ar[i * 2]];
+    }
+}
+
+/// Decodes a single block of palette indices of the given palettized `format` by looking each
+/// index up in `colors`.
+fn decode_indexed_block(
+    format: GVRDataFormat,
+    block: &[u8],
+    colors: &[[u8; 4]],
+    out: &mut [[u8; 4]],
+) {
+    let lookup = |index: usize| colors.get(index).copied().unwrap_or_default();
+
+    match format {
+        GVRDataFormat::C4 => {
+            for (i, color) in out.iter_mut().enumerate() {
+                // The first pixel is stored in the upper nibble
+                let byte = block[i / 2];
+                let index = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+                *color = lookup(index.into());
+            }
+        }
+        GVRDataFormat::C8 => {
+            for (color, &index) in out.iter_mut().zip(block) {
+                *color = lookup(index.into());
+            }
+        }
+        _ => {
+            for (color, texel) in out.iter_mut().zip(block.chunks_exact(2)) {
+                // The upper 2 bits are unused
+                let index = u16::from_be_bytes([texel[0], texel[1]]) & 0x3FFF;
+                *color = lookup(index.into());
+            }
+        }
+    }
+}
+
+/// Expands a 3-bit channel value into the full 8-bit range.
+pub(crate) fn expand3(value: u16) -> u8 {
+    let value = value as u8;
+    (value << 5) | (value << 2) | (value >> 1)
+}
+
+/// Expands a 4-bit channel value into the full 8-bit range.
+pub(crate) fn expand4(value: u16) -> u8 {
+    let value = value as u8;
+    (value << 4) | value
+}
+
+/// Expands a 5-bit channel value into the full 8-bit range.
+pub(crate) fn expand5(value: u16) -> u8 {
+    let value = value as u8;
+    (value << 3) | (value >> 2)
+}
+
+/// Expands a 6-bit channel value into the full 8-bit range.
+pub(crate) fn expand6(value: u16) -> u8 {
+    let value = value as u8;
+    (value << 2) | (value >> 4)
+}
+
+/// Converts a single IA8 value (alpha in the upper byte, intensity in the lower byte) into an
+/// RGBA8 color.
+pub(crate) fn ia8_to_rgba(value: u16) -> [u8; 4] {
+    let [alpha, intensity] = value.to_be_bytes();
+    [intensity, intensity, intensity, alpha]
+}
+
+/// Converts a single RGB565 color value into an RGBA8 color.
+pub(crate) fn rgb565_to_rgba(value: u16) -> [u8; 4] {
+    [
+        expand5((value >> 11) & 0x1F),
+        expand6((value >> 5) & 0x3F),
+        expand5(value & 0x1F),
+        0xFF,
+    ]
+}
+
+/// Converts a single RGB5A3 color value into an RGBA8 color.
+///
+/// If the top bit is set, the color is stored as opaque RGB555, otherwise as RGB444 with a 3-bit
+/// alpha value.
+pub(crate) fn rgb5a3_to_rgba(value: u16) -> [u8; 4] {
+    if value & 0x8000 != 0 {
+        [
+            expand5((value >> 10) & 0x1F),
+            expand5((value >> 5) & 0x1F),
+            expand5(value & 0x1F),
+            0xFF,
+        ]
+    } else {
+        [
+            expand4((value >> 8) & 0xF),
+            expand4((value >> 4) & 0xF),
+            expand4(value & 0xF),
+            expand3((value >> 12) & 0x7),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::GVRPaletteFormat;
+
+    #[test]
+    fn decode_rgb565() {
+        // 4x4 block, first texel red, second green, third blue, rest white
+        let mut data = vec![0xFF; 32];
+        data[0..6].copy_from_slice(&[0xF8, 0x00, 0x07, 0xE0, 0x00, 0x1F]);
+
+        let image = decode_texture_data(GVRDataFormat::RGB565, &data, 4, 4, None).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(1, 0), [0, 0xFF, 0, 0xFF]);
+        assert_eq!(image.get_pixel(2, 0), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(3, 3), [0xFF; 4]);
+    }
+
+    #[test]
+    fn decode_rgb5a3() {
+        assert_eq!(rgb5a3_to_rgba(0xFC00), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(rgb5a3_to_rgba(0x4F0F), [0xFF, 0, 0xFF, 0x92]);
+        assert_eq!(rgb5a3_to_rgba(0x0000), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn decode_argb8() {
+        let mut data = vec![0; 64];
+        // Texel 5 (x = 1, y = 1): A = 0x80, R = 0x11, G = 0x22, B = 0x33
+        data[10..12].copy_from_slice(&[0x80, 0x11]);
+        data[32 + 10..32 + 12].copy_from_slice(&[0x22, 0x33]);
+
+        let image = decode_texture_data(GVRDataFormat::ARGB8, &data, 4, 4, None).unwrap();
+        assert_eq!(image.get_pixel(1, 1), [0x11, 0x22, 0x33, 0x80]);
+        assert_eq!(image.get_pixel(0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn decode_i4() {
+        // 16x8 texture made of two 8x8 blocks
+        let mut data = vec![0; 64];
+        data[0] = 0xF1; // (0, 0) and (1, 0)
+        data[4] = 0x80; // (0, 1)
+        data[32 + 31] = 0x0A; // (15, 7)
+
+        let image = decode_texture_data(GVRDataFormat::I4, &data, 16, 8, None).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0xFF; 4]);
+        assert_eq!(image.get_pixel(1, 0), [0x11; 4]);
+        assert_eq!(image.get_pixel(0, 1), [0x88; 4]);
+        assert_eq!(image.get_pixel(14, 7), [0; 4]);
+        assert_eq!(image.get_pixel(15, 7), [0xAA; 4]);
+    }
+
+    #[test]
+    fn decode_i8() {
+        // 8x8 texture made of two 8x4 blocks
+        let data: Vec<u8> = (0..64).collect();
+
+        let image = decode_texture_data(GVRDataFormat::I8, &data, 8, 8, None).unwrap();
+        assert_eq!(image.get_pixel(7, 0), [7; 4]);
+        assert_eq!(image.get_pixel(0, 1), [8; 4]);
+        assert_eq!(image.get_pixel(0, 4), [32; 4]);
+        assert_eq!(image.get_pixel(7, 7), [63; 4]);
+    }
+
+    #[test]
+    fn decode_ia4() {
+        let mut data = vec![0; 32];
+        data[9] = 0x3C; // (1, 1)
+
+        let image = decode_texture_data(GVRDataFormat::IA4, &data, 8, 4, None).unwrap();
+        assert_eq!(image.get_pixel(1, 1), [0xCC, 0xCC, 0xCC, 0x33]);
+        assert_eq!(image.get_pixel(0, 0), [0; 4]);
+    }
+
+    #[test]
+    fn decode_ia8() {
+        // 8x4 texture made of two 4x4 blocks
+        let mut data = vec![0; 64];
+        data[0..2].copy_from_slice(&[0x40, 0xC0]); // (0, 0)
+        data[32 + 30..].copy_from_slice(&[0xFF, 0x10]); // (7, 3)
+
+        let image = decode_texture_data(GVRDataFormat::IA8, &data, 8, 4, None).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0xC0, 0xC0, 0xC0, 0x40]);
+        assert_eq!(image.get_pixel(7, 3), [0x10, 0x10, 0x10, 0xFF]);
+        assert_eq!(image.get_pixel(4, 0), [0; 4]);
+    }
+
+    #[test]
+    fn decode_c4() {
+        let palette = GVRPalette::new(GVRPaletteFormat::RGB565, vec![0x0000, 0xF800]);
+        let mut data = vec![0; 32];
+        data[0] = 0x01; // (0, 0) and (1, 0)
+        data[31] = 0x1F; // (6, 7) and (7, 7), the latter outside of the palette
+
+        let image = decode_texture_data(GVRDataFormat::C4, &data, 8, 8, Some(&palette)).unwrap();
+        assert_eq!(image.get_pixel(0, 0), [0, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(1, 0), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(6, 7), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(7, 7), [0; 4]);
+    }
+
+    #[test]
+    fn decode_c8_and_c14x2() {
+        let mut entries = vec![0; 256];
+        entries[0x80] = 0x40C0;
+        let palette = GVRPalette::new(GVRPaletteFormat::IA8, entries);
+
+        let mut data = vec![0; 32];
+        data[8] = 0x80; // (0, 1)
+        let image = decode_texture_data(GVRDataFormat::C8, &data, 8, 4, Some(&palette)).unwrap();
+        assert_eq!(image.get_pixel(0, 1), [0xC0, 0xC0, 0xC0, 0x40]);
+
+        let mut data = vec![0; 32];
+        data[2..4].copy_from_slice(&[0xC0, 0x80]); // (1, 0), with the unused upper bits set
+        let image = decode_texture_data(GVRDataFormat::C14X2, &data, 4, 4, Some(&palette)).unwrap();
+        assert_eq!(image.get_pixel(1, 0), [0xC0, 0xC0, 0xC0, 0x40]);
+
+        let err = decode_texture_data(GVRDataFormat::C8, &data, 8, 4, None).unwrap_err();
+        assert_eq!(err, GVRDecodeError::MissingPalette);
+    }
+
+    #[test]
+    fn read_texels() {
+        // 16x8 I4 texture made of two 8x8 blocks
+        let mut data = vec![0; 64];
+        data[0] = 0x12;
+        data[32 + 4] = 0x0A;
+        assert_eq!(
+            read_texel(GVRDataFormat::I4, &data, 16, 1, 0),
+            Some(GVRTexel::Value(0x2))
+        );
+        assert_eq!(
+            read_texel(GVRDataFormat::I4, &data, 16, 9, 1),
+            Some(GVRTexel::Value(0xA))
+        );
+        assert_eq!(read_texel(GVRDataFormat::I4, &data, 16, 16, 0), None);
+        assert_eq!(read_texel(GVRDataFormat::I4, &data, 16, 0, 8), None);
+
+        // The upper 2 bits of C14X2 texels aren't part of the palette index
+        let mut data = vec![0; 32];
+        data[4..6].copy_from_slice(&[0xC0, 0x05]);
+        assert_eq!(
+            read_texel(GVRDataFormat::C14X2, &data, 4, 2, 0),
+            Some(GVRTexel::Value(0x0005))
+        );
+
+        let mut data = vec![0; 64];
+        data[10..12].copy_from_slice(&[0x80, 0x11]);
+        data[32 + 10..32 + 12].copy_from_slice(&[0x22, 0x33]);
+        assert_eq!(
+            read_texel(GVRDataFormat::ARGB8, &data, 4, 1, 1),
+            Some(GVRTexel::Value(0x80112233))
+        );
+
+        // The bottom right sub-block of a CMPR block
+        let mut data = vec![0; 32];
+        data[24..32].copy_from_slice(&[0xF8, 0x00, 0x00, 0x1F, 0, 0b0001_1011, 0, 0]);
+        assert_eq!(
+            read_texel(GVRDataFormat::CMPR, &data, 8, 6, 5),
+            Some(GVRTexel::Cmpr {
+                color0: 0xF800,
+                color1: 0x001F,
+                index: 0b10,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_block_order() {
+        // 8x4 RGB565 texture made of two blocks, the second one fully white
+        let mut data = vec![0; 64];
+        data[32..].fill(0xFF);
+
+        let image = decode_texture_data(GVRDataFormat::RGB565, &data, 8, 4, None).unwrap();
+        assert_eq!(image.get_pixel(3, 3), [0, 0, 0, 0xFF]);
+        assert_eq!(image.get_pixel(4, 0), [0xFF; 4]);
+    }
+
+    #[test]
+    fn decode_partial_block() {
+        // 2x2 texture still occupies a full block
+        let image = decode_texture_data(GVRDataFormat::RGB565, &[0xFF; 32], 2, 2, None).unwrap();
+        assert_eq!(image.pixels.len(), 2 * 2 * 4);
+
+        let err = decode_texture_data(GVRDataFormat::RGB565, &[0xFF; 16], 2, 2, None).unwrap_err();
+        assert_eq!(
+            err,
+            GVRDecodeError::NotEnoughData {
+                expected: 32,
+                found: 16
+            }
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! This module contains the functionality to compare GVR textures with each other, both by their
+//! headers and data, and visually by their decoded pixels.
+
+use std::{borrow::Borrow, fmt};
+
+use super::{decode::GVRDecodeError, image::RGBAImage, GVRTexture};
+
+/// A header field that differs between two textures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GVRHeaderDifference {
+    /// The name of the header field.
+    pub field: &'static str,
+    /// The value of the field in the old texture.
+    pub old: String,
+    /// The value of the field in the new texture.
+    pub new: String,
+}
+
+impl fmt::Display for GVRHeaderDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+/// The visual difference between two decoded images of the same dimensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GVRImageDiff {
+    /// The peak signal-to-noise ratio of the new image compared to the old one, in decibels,
+    /// over all four channels. This is infinite if the images are identical.
+    pub psnr: f64,
+    /// The biggest difference of a single channel between the images.
+    pub max_error: u8,
+    /// The amount of pixels that differ between the images.
+    pub differing_pixels: usize,
+    /// An image showing how much each pixel differs, from black for identical pixels through red
+    /// and yellow to white for the pixels that differ by [`GVRImageDiff::max_error`].
+    pub heatmap: RGBAImage,
+}
+
+impl GVRImageDiff {
+    /// Whether the images are identical.
+    pub fn is_identical(&self) -> bool {
+        self.max_error == 0
+    }
+}
+
+/// The outcome of visually comparing two textures.
+#[derive(Debug, Clone)]
+pub enum GVRVisualDiff {
+    /// The textures were decoded and compared.
+    Compared(GVRImageDiff),
+    /// The textures have different dimensions, so they can't be compared pixel by pixel.
+    DimensionsDiffer,
+    /// One of the textures couldn't be decoded.
+    Undecodable(GVRDecodeError),
+}
+
+/// The differences between two textures.
+#[derive(Debug, Clone)]
+pub struct GVRTextureDiff {
+    /// The header fields that differ between the textures.
+    pub header_differences: Vec<GVRHeaderDifference>,
+    /// Whether the full texture files are byte for byte identical.
+    pub bytes_identical: bool,
+    /// The visual difference between the base textures.
+    pub visual: GVRVisualDiff,
+}
+
+impl GVRTextureDiff {
+    /// Whether the textures look the same, even if their bytes may differ.
+    pub fn is_visually_identical(&self) -> bool {
+        match &self.visual {
+            GVRVisualDiff::Compared(diff) => diff.is_identical(),
+            _ => self.bytes_identical,
+        }
+    }
+}
+
+/// How a texture of one texture list relates to a texture of another. See [`diff_textures()`].
+#[derive(Debug, Clone)]
+pub enum GVRTextureMatch {
+    /// The texture at `old` was matched with the texture at `new`.
+    Matched {
+        /// The index of the texture in the old texture list.
+        old: usize,
+        /// The index of the texture in the new texture list.
+        new: usize,
+        /// The differences between the textures.
+        diff: GVRTextureDiff,
+    },
+    /// The texture at this index of the old texture list has no match in the new one.
+    Removed(usize),
+    /// The texture at this index of the new texture list has no match in the old one.
+    Added(usize),
+}
+
+/// Compares two images of the same dimensions pixel by pixel. Returns [`None`] if the
+/// dimensions differ.
+pub fn diff_images(old: &RGBAImage, new: &RGBAImage) -> Option<GVRImageDiff> {
+    if old.width != new.width || old.height != new.height {
+        return None;
+    }
+
+    let pixel_errors: Vec<u8> = old
+        .pixels
+        .chunks_exact(4)
+        .zip(new.pixels.chunks_exact(4))
+        .map(|(a, b)| a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap())
+        .collect();
+
+    let squared_error: u64 = old
+        .pixels
+        .iter()
+        .zip(&new.pixels)
+        .map(|(a, b)| u64::from(a.abs_diff(*b)).pow(2))
+        .sum();
+    let psnr = if squared_error == 0 {
+        f64::INFINITY
+    } else {
+        let mse = squared_error as f64 / old.pixels.len() as f64;
+        10.0 * (255.0 * 255.0 / mse).log10()
+    };
+
+    let max_error = pixel_errors.iter().copied().max().unwrap_or(0);
+    let heatmap_pixels = pixel_errors
+        .iter()
+        .flat_map(|&error| heat_color(error, max_error))
+        .collect();
+
+    Some(GVRImageDiff {
+        psnr,
+        max_error,
+        differing_pixels: pixel_errors.iter().filter(|&&error| error != 0).count(),
+        heatmap: RGBAImage::from_pixels(old.width, old.height, heatmap_pixels).unwrap(),
+    })
+}
+
+/// Maps the `error` of a pixel to a color on a black-red-yellow-white scale, relative to the
+/// biggest error of the image.
+fn heat_color(error: u8, max_error: u8) -> [u8; 4] {
+    if error == 0 {
+        return [0, 0, 0, 0xFF];
+    }
+
+    let t = f32::from(error) / f32::from(max_error) * 3.0;
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(t), channel(t - 1.0), channel(t - 2.0), 0xFF]
+}
+
+/// Compares two lists of textures, such as the textures of two texture archives.
+///
+/// Textures are matched by name first. The textures left without a match are then matched by
+/// index, as long as neither of them has a name that exists in the other list. Everything else
+/// is reported as removed or added. The matches are ordered by the index in the old list, with
+/// added textures last.
+pub fn diff_textures<T: Borrow<GVRTexture>>(old: &[T], new: &[T]) -> Vec<GVRTextureMatch> {
+    let old: Vec<&GVRTexture> = old.iter().map(Borrow::borrow).collect();
+    let new: Vec<&GVRTexture> = new.iter().map(Borrow::borrow).collect();
+
+    let mut new_matched = vec![false; new.len()];
+    let mut matched_with: Vec<Option<usize>> = vec![None; old.len()];
+
+    for (i, tex) in old.iter().enumerate() {
+        let found = new
+            .iter()
+            .enumerate()
+            .position(|(j, other)| !new_matched[j] && other.name == tex.name);
+        if let Some(j) = found {
+            new_matched[j] = true;
+            matched_with[i] = Some(j);
+        }
+    }
+
+    for (i, tex) in old.iter().enumerate() {
+        let Some(other) = new.get(i) else {
+            continue;
+        };
+        let name_exists = |name: &str, list: &[&GVRTexture]| list.iter().any(|t| t.name == name);
+
+        if matched_with[i].is_none()
+            && !new_matched[i]
+            && !name_exists(&tex.name, &new)
+            && !name_exists(&other.name, &old)
+        {
+            new_matched[i] = true;
+            matched_with[i] = Some(i);
+        }
+    }
+
+    let mut matches: Vec<GVRTextureMatch> = matched_with
+        .into_iter()
+        .enumerate()
+        .map(|(i, j)| match j {
+            Some(j) => GVRTextureMatch::Matched {
+                old: i,
+                new: j,
+                diff: old[i].diff(new[j]),
+            },
+            None => GVRTextureMatch::Removed(i),
+        })
+        .collect();
+
+    matches.extend(
+        new_matched
+            .into_iter()
+            .enumerate()
+            .filter(|(_, matched)| !matched)
+            .map(|(j, _)| GVRTextureMatch::Added(j)),
+    );
+
+    matches
+}
+
+impl GVRTexture {
+    /// Compares this texture, as the old texture, with the `new` texture. The names of the
+    /// textures aren't compared.
+    pub fn diff(&self, new: &GVRTexture) -> GVRTextureDiff {
+        let (old_header, new_header) = (&self.header, &new.header);
+        let mut header_differences = vec![];
+        let mut compare = |field, old: String, new: String| {
+            if old != new {
+                header_differences.push(GVRHeaderDifference { field, old, new });
+            }
+        };
+
+        compare(
+            "format",
+            old_header.data_format.to_string(),
+            new_header.data_format.to_string(),
+        );
+        compare(
+            "width",
+            old_header.width.to_string(),
+            new_header.width.to_string(),
+        );
+        compare(
+            "height",
+            old_header.height.to_string(),
+            new_header.height.to_string(),
+        );
+        compare(
+            "palette format",
+            old_header.palette_format.to_string(),
+            new_header.palette_format.to_string(),
+        );
+        compare(
+            "flags",
+            format!("0x{:02X}", old_header.flags),
+            format!("0x{:02X}", new_header.flags),
+        );
+        compare(
+            "global index",
+            old_header.global_index.to_string(),
+            new_header.global_index.to_string(),
+        );
+        compare(
+            "mip levels",
+            self.mip_levels().len().to_string(),
+            new.mip_levels().len().to_string(),
+        );
+
+        let visual = match (self.decode(), new.decode()) {
+            (Ok(old_image), Ok(new_image)) => match diff_images(&old_image, &new_image) {
+                Some(diff) => GVRVisualDiff::Compared(diff),
+                None => GVRVisualDiff::DimensionsDiffer,
+            },
+            (Err(err), _) | (_, Err(err)) => GVRVisualDiff::Undecodable(err),
+        };
+
+        GVRTextureDiff {
+            header_differences,
+            bytes_identical: self.data.get_ref() == new.data.get_ref(),
+            visual,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::{encode::GVREncodeOptions, GVRDataFormat};
+
+    fn make_texture(name: &str, color: [u8; 4], format: GVRDataFormat) -> GVRTexture {
+        let mut image = RGBAImage::new(8, 8);
+        for y in 0..8 {
+            for x in 0..8 {
+                image.set_pixel(x, y, color);
+            }
+        }
+
+        GVRTexture::encode(name.into(), &image, &GVREncodeOptions::new(format)).unwrap()
+    }
+
+    #[test]
+    fn diff_identical_images() {
+        let image = RGBAImage::new(4, 4);
+        let diff = diff_images(&image, &image).unwrap();
+
+        assert!(diff.is_identical());
+        assert_eq!(diff.psnr, f64::INFINITY);
+        assert_eq!(diff.differing_pixels, 0);
+        assert_eq!(diff.heatmap.get_pixel(0, 0), [0, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn diff_changed_images() {
+        let old = RGBAImage::new(2, 2);
+        let mut new = RGBAImage::new(2, 2);
+        new.set_pixel(1, 0, [10, 0, 0, 0]);
+        new.set_pixel(0, 1, [0, 0, 20, 0]);
+
+        let diff = diff_images(&old, &new).unwrap();
+        assert_eq!(diff.max_error, 20);
+        assert_eq!(diff.differing_pixels, 2);
+        // MSE = (10^2 + 20^2) / 16
+        let expected = 10.0 * (255.0f64 * 255.0 / 31.25).log10();
+        assert!((diff.psnr - expected).abs() < 1e-9);
+        assert_eq!(diff.heatmap.get_pixel(0, 1), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(diff.heatmap.get_pixel(1, 0), [0xFF, 0x80, 0, 0xFF]);
+
+        assert!(diff_images(&old, &RGBAImage::new(2, 1)).is_none());
+    }
+
+    #[test]
+    fn diff_texture_headers() {
+        let old = make_texture("a", [0xFF, 0, 0, 0xFF], GVRDataFormat::RGB565);
+        let new = make_texture("a", [0xFF, 0, 0, 0xFF], GVRDataFormat::ARGB8);
+
+        let diff = old.diff(&new);
+        assert!(!diff.bytes_identical);
+        assert!(diff.is_visually_identical());
+        assert_eq!(
+            diff.header_differences,
+            vec![GVRHeaderDifference {
+                field: "format",
+                old: "RGB565".into(),
+                new: "ARGB8".into(),
+            }]
+        );
+
+        let diff = old.diff(&old.clone());
+        assert!(diff.bytes_identical);
+        assert!(diff.header_differences.is_empty());
+    }
+
+    #[test]
+    fn match_textures_by_name_and_index() {
+        let red = [0xFF, 0, 0, 0xFF];
+        let old = [
+            make_texture("a", red, GVRDataFormat::RGB565),
+            make_texture("b", red, GVRDataFormat::RGB565),
+            make_texture("c", red, GVRDataFormat::RGB565),
+        ];
+        let new = [
+            make_texture("b", [0, 0, 0xFF, 0xFF], GVRDataFormat::RGB565),
+            make_texture("renamed", red, GVRDataFormat::RGB565),
+            make_texture("a", red, GVRDataFormat::RGB565),
+            make_texture("added", red, GVRDataFormat::RGB565),
+        ];
+
+        let matches = diff_textures(&old, &new);
+        let summary: Vec<_> = matches
+            .iter()
+            .map(|m| match m {
+                GVRTextureMatch::Matched { old, new, diff } => {
+                    (Some(*old), Some(*new), diff.is_visually_identical())
+                }
+                GVRTextureMatch::Removed(old) => (Some(*old), None, false),
+                GVRTextureMatch::Added(new) => (None, Some(*new), false),
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (Some(0), Some(2), true),
+                (Some(1), Some(0), false),
+                (Some(2), None, false),
+                (None, Some(1), false),
+                (None, Some(3), false),
+            ]
+        );
+
+        // A texture renamed in place is matched by its index
+        let mut renamed = old.clone();
+        renamed[2].name = "c_renamed".into();
+        let matches = diff_textures(&old, &renamed);
+        assert_eq!(matches.len(), 3);
+        assert!(matches!(
+            &matches[2],
+            GVRTextureMatch::Matched { old: 2, new: 2, diff } if diff.bytes_identical
+        ));
+    }
+}
@@ -0,0 +1,450 @@
+//! This module contains the functionality to work with Dolphin custom texture packs, which Dolphin
+//! loads through its "Load Custom Textures" feature.
+//!
+//! Dolphin identifies every texture by a file name like `tex1_64x64_m_0123456789abcdef_14`, made
+//! up of the texture dimensions, whether it has mipmaps, an XXH64 hash of the base texture data
+//! (and of the used part of the palette for palettized textures) and the texture format.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use xxhash_rust::xxh64::xxh64;
+
+use super::{
+    constraints::GVRConstraintViolation, decode::GVRDecodeError, encode::GVREncodeOptions,
+    interchange::GVRInterchangeError, GVRDataFormat, GVRTexture,
+};
+
+/// The prefix of every texture file name in a Dolphin custom texture pack.
+const DOLPHIN_TEXTURE_PREFIX: &str = "tex1_";
+
+/// Represents the name Dolphin gives a texture in custom texture packs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DolphinTextureName {
+    /// The width of the texture in pixels.
+    pub width: u16,
+    /// The height of the texture in pixels.
+    pub height: u16,
+    /// Whether the texture has mipmaps.
+    pub has_mipmaps: bool,
+    /// The hash of the base texture data.
+    pub texture_hash: u64,
+    /// The hash of the used part of the palette, for palettized textures.
+    pub tlut_hash: Option<u64>,
+    /// The format of the texture.
+    pub format: GVRDataFormat,
+}
+
+impl DolphinTextureName {
+    /// Returns the name with `$` in place of the palette hash, which Dolphin accepts as a wildcard
+    /// matching any palette. Returns [`None`] if the texture isn't palettized.
+    pub fn wildcard(&self) -> Option<String> {
+        self.tlut_hash?;
+
+        Some(format!(
+            "{}_{:016x}_$_{}",
+            self.base_name(),
+            self.texture_hash,
+            self.format.code()
+        ))
+    }
+
+    fn base_name(&self) -> String {
+        let mipmaps = if self.has_mipmaps { "_m" } else { "" };
+        format!(
+            "{DOLPHIN_TEXTURE_PREFIX}{}x{}{mipmaps}",
+            self.width, self.height
+        )
+    }
+}
+
+impl fmt::Display for DolphinTextureName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{:016x}", self.base_name(), self.texture_hash)?;
+
+        if let Some(tlut_hash) = self.tlut_hash {
+            write!(f, "_{tlut_hash:016x}")?;
+        }
+
+        write!(f, "_{}", self.format.code())
+    }
+}
+
+impl GVRTexture {
+    /// Calculates the name Dolphin gives this texture in custom texture packs.
+    ///
+    /// For palettized textures, only the palette entries the texture actually uses are hashed,
+    /// like Dolphin does. Entries past the end of the palette are hashed as zeroes, as there's no
+    /// way to know what the game has in memory there.
+    pub fn dolphin_texture_name(&self) -> Result<DolphinTextureName, GVRDecodeError> {
+        let header = &self.header;
+        let format = header.data_format;
+
+        let base_level = header
+            .mip_levels()
+            .first()
+            .copied()
+            .ok_or(GVRDecodeError::MipLevelOutOfRange { level: 0, count: 0 })?;
+        let data = self
+            .mip_level_data(0)
+            .ok_or(GVRDecodeError::NotEnoughData {
+                expected: base_level.range().end,
+                found: self.data.get_ref().len(),
+            })?;
+
+        let tlut_hash = if format.is_palettized() {
+            let palette = self.palette().ok_or(GVRDecodeError::MissingPalette)?;
+            let (min, max) = palette_index_range(format, data);
+
+            let mut tlut = palette.entry_bytes();
+            tlut.resize(tlut.len().max((max + 1) * 2), 0);
+
+            Some(xxh64(&tlut[min * 2..(max + 1) * 2], 0))
+        } else {
+            None
+        };
+
+        Ok(DolphinTextureName {
+            width: header.width,
+            height: header.height,
+            has_mipmaps: header.has_mipmaps() && self.mip_levels().len() > 1,
+            texture_hash: xxh64(data, 0),
+            tlut_hash,
+            format,
+        })
+    }
+}
+
+/// Finds the smallest and the biggest palette index used in the palettized texture `data` of the
+/// given `format`. Empty `data` is treated as only using the first index.
+fn palette_index_range(format: GVRDataFormat, data: &[u8]) -> (usize, usize) {
+    let indices: Box<dyn Iterator<Item = usize>> = match format {
+        GVRDataFormat::C4 => Box::new(
+            data.iter()
+                .flat_map(|&byte| [usize::from(byte >> 4), usize::from(byte & 0xF)]),
+        ),
+        GVRDataFormat::C8 => Box::new(data.iter().map(|&byte| usize::from(byte))),
+        _ => Box::new(
+            data.chunks_exact(2)
+                .map(|index| usize::from(u16::from_be_bytes([index[0], index[1]]) & 0x3FFF)),
+        ),
+    };
+
+    indices
+        .fold(None, |range, index| match range {
+            Some((min, max)) => Some((index.min(min), index.max(max))),
+            None => Some((index, index)),
+        })
+        .unwrap_or((0, 0))
+}
+
+/// Describes the outcome of exporting textures into, or importing textures from a Dolphin custom
+/// texture pack.
+#[derive(Debug, Default)]
+pub struct DolphinPackReport {
+    /// The names of the textures that were exported or replaced.
+    pub processed: Vec<String>,
+    /// The names of the textures that couldn't be exported or replaced, with the reason why.
+    pub failed: Vec<(String, GVRInterchangeError)>,
+    /// The names of the replaced textures that break the constraints of the GX hardware, with
+    /// the constraints they break. See [`GVRTexture::check_constraints()`] for more details.
+    pub violations: Vec<(String, Vec<GVRConstraintViolation>)>,
+}
+
+impl DolphinPackReport {
+    /// Adds the results of `other` into this report.
+    pub fn merge(&mut self, other: DolphinPackReport) {
+        self.processed.extend(other.processed);
+        self.failed.extend(other.failed);
+        self.violations.extend(other.violations);
+    }
+}
+
+/// Exports the base texture of every texture in `textures` into the folder given by `path` as a
+/// PNG image, named the way Dolphin expects them in custom texture packs.
+///
+/// Textures that are identical to an already exported one are only exported once.
+pub fn export_dolphin_pack<'a>(
+    textures: impl IntoIterator<Item = &'a GVRTexture>,
+    path: &Path,
+) -> io::Result<DolphinPackReport> {
+    let mut report = DolphinPackReport::default();
+    let mut exported_names = vec![];
+
+    for tex in textures {
+        let name = match tex.dolphin_texture_name() {
+            Ok(name) => name.to_string(),
+            Err(err) => {
+                report.failed.push((tex.name.clone(), err.into()));
+                continue;
+            }
+        };
+
+        if exported_names.contains(&name) {
+            continue;
+        }
+
+        match tex.to_png() {
+            Ok(png) => std::fs::write(path.join(format!("{name}.png")), png)?,
+            Err(err) => {
+                report.failed.push((tex.name.clone(), err));
+                continue;
+            }
+        }
+
+        report.processed.push(tex.name.clone());
+        exported_names.push(name);
+    }
+
+    Ok(report)
+}
+
+/// Represents a Dolphin custom texture pack on disk, whose PNG images can be imported back into
+/// GVR textures.
+pub struct DolphinTexturePack {
+    /// All the PNG images in the pack, keyed by their lowercase file name without the extension.
+    images: HashMap<String, PathBuf>,
+}
+
+impl DolphinTexturePack {
+    /// Opens the Dolphin custom texture pack in the folder given by `path`. Subfolders are
+    /// searched as well, like Dolphin does.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut images = HashMap::new();
+        let mut folders = vec![path.to_path_buf()];
+
+        while let Some(folder) = folders.pop() {
+            for entry in std::fs::read_dir(folder)? {
+                let entry_path = entry?.path();
+
+                if entry_path.is_dir() {
+                    folders.push(entry_path);
+                    continue;
+                }
+
+                let is_png = entry_path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+                let stem = entry_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_ascii_lowercase());
+
+                if let (true, Some(stem)) = (is_png, stem) {
+                    if stem.starts_with(DOLPHIN_TEXTURE_PREFIX) {
+                        images.insert(stem, entry_path);
+                    }
+                }
+            }
+        }
+
+        Ok(Self { images })
+    }
+
+    /// Returns the amount of texture images in this pack.
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Whether this pack has no texture images in it.
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Finds the image in this pack for the texture with the given Dolphin `name`. An image with
+    /// a wildcard palette hash is used if there isn't one for the exact palette.
+    pub fn find_image(&self, name: &DolphinTextureName) -> Option<&Path> {
+        self.images
+            .get(&name.to_string())
+            .or_else(|| self.images.get(&name.wildcard()?))
+            .map(PathBuf::as_path)
+    }
+
+    /// Replaces every texture in `textures` that has an image in this pack with the image,
+    /// encoded to match the texture it replaces. The texture names are kept as they are. The
+    /// replacements are checked against the constraints of the GX hardware.
+    pub fn replace_textures<'a>(
+        &self,
+        textures: impl IntoIterator<Item = &'a mut GVRTexture>,
+    ) -> io::Result<DolphinPackReport> {
+        let mut report = DolphinPackReport::default();
+
+        for tex in textures {
+            let name = match tex.dolphin_texture_name() {
+                Ok(name) => name,
+                Err(err) => {
+                    report.failed.push((tex.name.clone(), err.into()));
+                    continue;
+                }
+            };
+
+            let Some(image_path) = self.find_image(&name) else {
+                continue;
+            };
+
+            let png = std::fs::read(image_path)?;
+            let options = GVREncodeOptions::matching(tex);
+
+            match GVRTexture::from_png(tex.name.clone(), &png, &options) {
+                Ok(replacement) => {
+                    report.processed.push(tex.name.clone());
+
+                    let violations = replacement.check_constraints();
+                    if !violations.is_empty() {
+                        report.violations.push((tex.name.clone(), violations));
+                    }
+                    *tex = replacement;
+                }
+                Err(err) => report.failed.push((tex.name.clone(), err)),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::{
+        image::RGBAImage, interchange::write_png, mipmap::GVRMipmaps, GVR_FLAG_MIPMAPS,
+        GVR_HEADER_SIZE,
+    };
+
+    fn make_image(width: u32, height: u32) -> RGBAImage {
+        let mut image = RGBAImage::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set_pixel(x, y, [(x * 16) as u8, (y * 16) as u8, 0x80, 0xFF]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn name_format() {
+        let mut name = DolphinTextureName {
+            width: 64,
+            height: 32,
+            has_mipmaps: true,
+            texture_hash: 0x0123456789ABCDEF,
+            tlut_hash: None,
+            format: GVRDataFormat::CMPR,
+        };
+        assert_eq!(name.to_string(), "tex1_64x32_m_0123456789abcdef_14");
+        assert_eq!(name.wildcard(), None);
+
+        name.has_mipmaps = false;
+        name.tlut_hash = Some(0xFEDC);
+        name.format = GVRDataFormat::C8;
+        assert_eq!(
+            name.to_string(),
+            "tex1_64x32_0123456789abcdef_000000000000fedc_9"
+        );
+        assert_eq!(name.wildcard().unwrap(), "tex1_64x32_0123456789abcdef_$_9");
+    }
+
+    #[test]
+    fn texture_name_hashes() {
+        let mut options = GVREncodeOptions::new(GVRDataFormat::RGB5A3);
+        options.mipmaps = GVRMipmaps::Full;
+        let tex = GVRTexture::encode(String::new(), &make_image(8, 8), &options).unwrap();
+        assert_ne!(tex.header.flags & GVR_FLAG_MIPMAPS, 0);
+
+        let name = tex.dolphin_texture_name().unwrap();
+        let data = &tex.data.get_ref()[GVR_HEADER_SIZE as usize..][..0x80];
+
+        assert_eq!((name.width, name.height), (8, 8));
+        assert!(name.has_mipmaps);
+        assert_eq!(name.texture_hash, xxh64(data, 0));
+        assert_eq!(name.tlut_hash, None);
+
+        // An edited header that doesn't describe any mip level
+        let mut tex = tex;
+        (tex.header.width, tex.header.height) = (0, 0);
+        assert_eq!(
+            tex.dolphin_texture_name().unwrap_err(),
+            GVRDecodeError::MipLevelOutOfRange { level: 0, count: 0 }
+        );
+    }
+
+    #[test]
+    fn palette_hash_covers_used_entries() {
+        // Indices 2 and 3 are the only ones used
+        assert_eq!(
+            palette_index_range(GVRDataFormat::C4, &[0x23, 0x32]),
+            (2, 3)
+        );
+        assert_eq!(
+            palette_index_range(GVRDataFormat::C8, &[0x10, 0x80]),
+            (0x10, 0x80)
+        );
+        assert_eq!(
+            palette_index_range(GVRDataFormat::C14X2, &[0xC0, 0x05, 0x00, 0x07]),
+            (5, 7)
+        );
+
+        let mut image = RGBAImage::new(8, 8);
+        for y in 0..8 {
+            image.set_pixel(0, y, [0xFF, 0, 0, 0xFF]);
+        }
+        let options = GVREncodeOptions::new(GVRDataFormat::C4);
+        let tex = GVRTexture::encode(String::new(), &image, &options).unwrap();
+
+        let data = tex.mip_level_data(0).unwrap();
+        let (min, max) = palette_index_range(GVRDataFormat::C4, data);
+        let tlut = tex.palette().unwrap().entry_bytes();
+
+        let name = tex.dolphin_texture_name().unwrap();
+        assert_eq!(
+            name.tlut_hash,
+            Some(xxh64(&tlut[min * 2..(max + 1) * 2], 0))
+        );
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let dir = std::env::temp_dir().join(format!("dolphin-pack-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+        let options = GVREncodeOptions::new(GVRDataFormat::ARGB8);
+        let tex = GVRTexture::encode("tex".into(), &make_image(8, 8), &options).unwrap();
+        let duplicate = tex.clone();
+
+        let report = export_dolphin_pack(&[tex.clone(), duplicate], &dir).unwrap();
+        assert_eq!(report.processed, vec!["tex".to_string()]);
+        assert!(report.failed.is_empty());
+
+        // Move the exported image into a subfolder and edit it
+        let name = tex.dolphin_texture_name().unwrap().to_string();
+        std::fs::remove_file(dir.join(format!("{name}.png"))).unwrap();
+        // Dolphin allows any size, but the hardware needs powers of two
+        let edited = make_image(12, 12);
+        let png = write_png(&edited).unwrap();
+        std::fs::write(dir.join("sub").join(format!("{name}.png")), png).unwrap();
+
+        let pack = DolphinTexturePack::open(&dir).unwrap();
+        assert_eq!(pack.len(), 1);
+
+        let mut textures = vec![tex];
+        let report = pack.replace_textures(&mut textures).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.processed, vec!["tex".to_string()]);
+        assert_eq!(
+            report.violations,
+            vec![(
+                "tex".to_string(),
+                vec![GVRConstraintViolation::NotPowerOfTwo {
+                    width: 12,
+                    height: 12
+                }]
+            )]
+        );
+        assert_eq!(textures[0].name, "tex");
+        assert_eq!(textures[0].header.data_format, GVRDataFormat::ARGB8);
+        assert_eq!(textures[0].decode().unwrap(), edited);
+    }
+}
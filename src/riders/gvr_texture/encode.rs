@@ -0,0 +1,487 @@
+//! This module contains the functionality to encode plain RGBA8 images into the tiled GameCube
+//! texture data of GVR textures.
+
+use std::fmt;
+
+use super::{
+    cmpr,
+    image::RGBAImage,
+    mipmap::{GVRMipmapFilter, GVRMipmaps},
+    palette::GVRPalette,
+    quantize, GVRDataFormat, GVRPaletteFormat, GVRTexture,
+};
+
+/// An error that occurred while encoding an image into a GVR texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GVREncodeError {
+    /// Encoding into the given format isn't supported.
+    UnsupportedFormat(GVRDataFormat),
+    /// The image dimensions can't be stored in a GVR texture.
+    InvalidDimensions {
+        /// The width of the image.
+        width: u32,
+        /// The height of the image.
+        height: u32,
+    },
+}
+
+impl fmt::Display for GVREncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVREncodeError::UnsupportedFormat(format) => {
+                write!(f, "encoding {format} textures is not supported")
+            }
+            GVREncodeError::InvalidDimensions { width, height } => {
+                write!(f, "{width}x{height} is not a valid texture size")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GVREncodeError {}
+
+/// How much effort is put into finding the best colors for each CMPR block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum CMPRQuality {
+    /// Picks the colors at both ends of each block's principal color axis.
+    Fast,
+    /// Additionally refines the colors with a least squares fit and a search of neighbouring
+    /// colors, and tries both block modes for opaque blocks.
+    #[default]
+    High,
+}
+
+/// Where the palette of a palettized texture gets stored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum GVRPalettePlacement {
+    /// The palette is stored in the texture itself, right before the texture data.
+    #[default]
+    Embedded,
+    /// The palette is stored in a separate GVPL file.
+    External,
+}
+
+/// The options used when encoding an image into a full GVR texture via
+/// [`GVRTexture::encode()`](super::GVRTexture::encode()).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GVREncodeOptions {
+    /// The format to encode the texture data into.
+    pub format: GVRDataFormat,
+    /// The global index stored in the GCIX header.
+    pub global_index: u32,
+    /// The quality used for CMPR encoding. Ignored for every other format.
+    pub cmpr_quality: CMPRQuality,
+    /// The format of the palette entries for palettized formats. Ignored for every other format.
+    pub palette_format: GVRPaletteFormat,
+    /// Whether the palette gets embedded into the texture, or is left to be stored in a separate
+    /// GVPL file. Ignored for non-palettized formats.
+    pub palette_placement: GVRPalettePlacement,
+    /// Whether to apply Floyd-Steinberg dithering when mapping the colors of the image to the
+    /// palette. Ignored for non-palettized formats.
+    pub dither: bool,
+    /// How many mip levels get generated for the texture.
+    pub mipmaps: GVRMipmaps,
+    /// The filter used to generate the mip levels.
+    pub mipmap_filter: GVRMipmapFilter,
+    /// Whether the mip levels are filtered in linear space instead of sRGB.
+    pub gamma_correct_mipmaps: bool,
+}
+
+impl Default for GVREncodeOptions {
+    fn default() -> Self {
+        Self {
+            format: GVRDataFormat::default(),
+            global_index: 0,
+            cmpr_quality: CMPRQuality::default(),
+            // RGB5A3 is the only palette format that can keep both color and alpha
+            palette_format: GVRPaletteFormat::RGB5A3,
+            palette_placement: GVRPalettePlacement::default(),
+            dither: false,
+            mipmaps: GVRMipmaps::default(),
+            mipmap_filter: GVRMipmapFilter::default(),
+            gamma_correct_mipmaps: false,
+        }
+    }
+}
+
+impl GVREncodeOptions {
+    /// Creates new [`GVREncodeOptions`] for the given `format`, using defaults for everything
+    /// else.
+    pub fn new(format: GVRDataFormat) -> Self {
+        Self {
+            format,
+            ..Default::default()
+        }
+    }
+
+    /// Creates new [`GVREncodeOptions`] that produce a texture matching `texture`, which is
+    /// useful when replacing it. This keeps the format, global index, palette format and
+    /// placement, and whether `texture` has mipmaps.
+    pub fn matching(texture: &GVRTexture) -> Self {
+        let header = &texture.header;
+
+        let mipmaps = if header.has_mipmaps() {
+            GVRMipmaps::Full
+        } else {
+            GVRMipmaps::None
+        };
+
+        let palette_placement = if header.has_external_palette() {
+            GVRPalettePlacement::External
+        } else {
+            GVRPalettePlacement::Embedded
+        };
+
+        let mut options = Self {
+            format: header.data_format,
+            global_index: header.global_index,
+            palette_placement,
+            mipmaps,
+            ..Default::default()
+        };
+
+        if header.data_format.is_palettized() {
+            options.palette_format = header.palette_format;
+        }
+
+        options
+    }
+}
+
+/// Checks that the dimensions of `image` can be stored in a GVR texture.
+fn check_dimensions(image: &RGBAImage) -> Result<(), GVREncodeError> {
+    if image.width == 0
+        || image.height == 0
+        || image.width > u16::MAX.into()
+        || image.height > u16::MAX.into()
+    {
+        return Err(GVREncodeError::InvalidDimensions {
+            width: image.width,
+            height: image.height,
+        });
+    }
+
+    Ok(())
+}
+
+/// Encodes the given `image` into tiled texture data, as per the given `options`.
+///
+/// Palettized formats aren't supported by this function, use
+/// [`encode_palettized_texture_data()`] for those instead.
+pub fn encode_texture_data(
+    image: &RGBAImage,
+    options: &GVREncodeOptions,
+) -> Result<Vec<u8>, GVREncodeError> {
+    let format = options.format;
+    check_dimensions(image)?;
+
+    let get_pixel = |x, y| image.get_pixel(x, y);
+
+    let encode_block: fn(&[[u8; 4]], &mut [u8]) = match format {
+        GVRDataFormat::I4 => encode_i4_block,
+        GVRDataFormat::I8 => encode_i8_block,
+        GVRDataFormat::IA4 => encode_ia4_block,
+        GVRDataFormat::IA8 => encode_ia8_block,
+        GVRDataFormat::RGB565 => encode_rgb565_block,
+        GVRDataFormat::RGB5A3 => encode_rgb5a3_block,
+        GVRDataFormat::ARGB8 => encode_argb8_block,
+        GVRDataFormat::CMPR => {
+            let quality = options.cmpr_quality;
+            let encode_block = |pixels: &[[u8; 4]], out: &mut [u8]| {
+                cmpr::encode_cmpr_block(pixels, out, quality);
+            };
+
+            return Ok(encode_tiled(
+                format,
+                image.width,
+                image.height,
+                get_pixel,
+                encode_block,
+            ));
+        }
+        _ => return Err(GVREncodeError::UnsupportedFormat(format)),
+    };
+
+    Ok(encode_tiled(
+        format,
+        image.width,
+        image.height,
+        get_pixel,
+        encode_block,
+    ))
+}
+
+/// Builds a palette for the given `image` and encodes it into tiled palette indices, as per the
+/// given `options`. Only C4 and C8 are supported.
+///
+/// The returned palette always has the full 16 or 256 entries of the format, with any unused
+/// entries left as 0.
+pub fn encode_palettized_texture_data(
+    image: &RGBAImage,
+    options: &GVREncodeOptions,
+) -> Result<(GVRPalette, Vec<u8>), GVREncodeError> {
+    let entry_count = match options.format {
+        GVRDataFormat::C4 => 16,
+        GVRDataFormat::C8 => 256,
+        format => return Err(GVREncodeError::UnsupportedFormat(format)),
+    };
+    check_dimensions(image)?;
+
+    let mut palette = quantize::build_palette(image, entry_count, options.palette_format);
+    palette.entries.resize(entry_count, 0);
+
+    let data = encode_with_palette(image, &palette, options)?;
+    Ok((palette, data))
+}
+
+/// Encodes the given `image` into tiled palette indices pointing into an already existing
+/// `palette`, as per the given `options`. Only C4 and C8 are supported.
+pub fn encode_with_palette(
+    image: &RGBAImage,
+    palette: &GVRPalette,
+    options: &GVREncodeOptions,
+) -> Result<Vec<u8>, GVREncodeError> {
+    let format = options.format;
+    check_dimensions(image)?;
+
+    let indices = quantize::map_to_palette(image, palette, options.dither);
+    let get_index = |x, y| indices[(y * image.width + x) as usize];
+
+    match format {
+        GVRDataFormat::C4 => Ok(encode_tiled(
+            format,
+            image.width,
+            image.height,
+            get_index,
+            |indices, out| {
+                for (byte, pair) in out.iter_mut().zip(indices.chunks_exact(2)) {
+                    // The first pixel is stored in the upper nibble
+                    *byte = ((pair[0] & 0xF) << 4) | (pair[1] & 0xF);
+                }
+            },
+        )),
+        GVRDataFormat::C8 => Ok(encode_tiled(
+            format,
+            image.width,
+            image.height,
+            get_index,
+            |indices, out| out.copy_from_slice(indices),
+        )),
+        _ => Err(GVREncodeError::UnsupportedFormat(format)),
+    }
+}
+
+/// Walks through all the blocks of a texture with the given dimensions, gathering the pixels of
+/// each one with `get_pixel` and encoding them with `encode_block`.
+///
+/// `encode_block` receives the pixels of a single block in row-major order, and has to write the
+/// encoded block into the given output slice. Pixels of blocks reaching past the edges of the
+/// texture are filled with the closest edge pixel.
+fn encode_tiled<T: Copy + Default>(
+    format: GVRDataFormat,
+    width: u32,
+    height: u32,
+    get_pixel: impl Fn(u32, u32) -> T,
+    encode_block: impl Fn(&[T], &mut [u8]),
+) -> Vec<u8> {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format.block_size();
+    let blocks_x = width.div_ceil(block_width);
+
+    let mut data = vec![0; format.data_size(width, height)];
+    let mut block_pixels = vec![T::default(); (block_width * block_height) as usize];
+
+    for (block_idx, block) in data.chunks_exact_mut(block_size).enumerate() {
+        let block_x = (block_idx as u32 % blocks_x) * block_width;
+        let block_y = (block_idx as u32 / blocks_x) * block_height;
+
+        for (i, pixel) in block_pixels.iter_mut().enumerate() {
+            let x = (block_x + i as u32 % block_width).min(width - 1);
+            let y = (block_y + i as u32 / block_width).min(height - 1);
+            *pixel = get_pixel(x, y);
+        }
+
+        encode_block(&block_pixels, block);
+    }
+
+    data
+}
+
+fn encode_i4_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (byte, pair) in out.iter_mut().zip(pixels.chunks_exact(2)) {
+        // The first pixel is stored in the upper nibble
+        *byte = (quantize(intensity(pair[0]), 4) << 4) | quantize(intensity(pair[1]), 4);
+    }
+}
+
+fn encode_i8_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (byte, color) in out.iter_mut().zip(pixels) {
+        *byte = intensity(*color);
+    }
+}
+
+fn encode_ia4_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (byte, color) in out.iter_mut().zip(pixels) {
+        *byte = (quantize(color[3], 4) << 4) | quantize(intensity(*color), 4);
+    }
+}
+
+fn encode_ia8_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (texel, color) in out.chunks_exact_mut(2).zip(pixels) {
+        texel.copy_from_slice(&[color[3], intensity(*color)]);
+    }
+}
+
+fn encode_rgb565_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (texel, color) in out.chunks_exact_mut(2).zip(pixels) {
+        texel.copy_from_slice(&rgba_to_rgb565(*color).to_be_bytes());
+    }
+}
+
+fn encode_rgb5a3_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    for (texel, color) in out.chunks_exact_mut(2).zip(pixels) {
+        texel.copy_from_slice(&rgba_to_rgb5a3(*color).to_be_bytes());
+    }
+}
+
+/// ARGB8 blocks are stored in two passes: first the alpha and red values of all 16 pixels,
+/// then the green and blue values.
+fn encode_argb8_block(pixels: &[[u8; 4]], out: &mut [u8]) {
+    let (ar, gb) = out.split_at_mut(32);
+
+    for (i, color) in pixels.iter().enumerate() {
+        ar[i * 2..i * 2 + 2].copy_from_slice(&[color[3], color[0]]);
+        gb[i * 2..i * 2 + 2].copy_from_slice(&[color[1], color[2]]);
+    }
+}
+
+/// Converts an RGBA8 color into a single IA8 value, with alpha in the upper byte and intensity in
+/// the lower byte.
+pub(crate) fn rgba_to_ia8(color: [u8; 4]) -> u16 {
+    u16::from_be_bytes([color[3], intensity(color)])
+}
+
+/// Reduces an 8-bit channel value to the given amount of `bits`, rounding to the nearest value.
+pub(crate) fn quantize(value: u8, bits: u32) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * max + 127) / 255) as u8
+}
+
+/// Calculates the perceived brightness of the given color, ignoring alpha.
+pub(crate) fn intensity(color: [u8; 4]) -> u8 {
+    let [r, g, b, _] = color.map(u32::from);
+    ((r * 299 + g * 587 + b * 114 + 500) / 1000) as u8
+}
+
+/// Converts an RGBA8 color into a single RGB565 color value, discarding alpha.
+pub(crate) fn rgba_to_rgb565(color: [u8; 4]) -> u16 {
+    (u16::from(quantize(color[0], 5)) << 11)
+        | (u16::from(quantize(color[1], 6)) << 5)
+        | u16::from(quantize(color[2], 5))
+}
+
+/// Converts an RGBA8 color into a single RGB5A3 color value.
+///
+/// Fully opaque colors are stored as RGB555 for extra color precision, everything else as RGB444
+/// with a 3-bit alpha value.
+pub(crate) fn rgba_to_rgb5a3(color: [u8; 4]) -> u16 {
+    let alpha = quantize(color[3], 3);
+
+    if alpha == 0x7 {
+        0x8000
+            | (u16::from(quantize(color[0], 5)) << 10)
+            | (u16::from(quantize(color[1], 5)) << 5)
+            | u16::from(quantize(color[2], 5))
+    } else {
+        (u16::from(alpha) << 12)
+            | (u16::from(quantize(color[0], 4)) << 8)
+            | (u16::from(quantize(color[1], 4)) << 4)
+            | u16::from(quantize(color[2], 4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::decode::decode_texture_data;
+
+    /// Makes an image with a gradient in every channel, so that no two pixels are the same.
+    fn make_gradient(width: u32, height: u32, grey: bool) -> RGBAImage {
+        let mut image = RGBAImage::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = (x * 255 / (width - 1)) as u8;
+                let w = (y * 255 / (height - 1)) as u8;
+                let color = if grey {
+                    [v, v, v, w]
+                } else {
+                    [v, w, v ^ w, 255 - w]
+                };
+                image.set_pixel(x, y, color);
+            }
+        }
+        image
+    }
+
+    /// Encodes and decodes `image`, checking that every channel is within `tolerance` of the
+    /// original, where `channels` selects which channels get compared.
+    fn assert_round_trip(
+        format: GVRDataFormat,
+        image: &RGBAImage,
+        tolerance: u8,
+        channels: &[usize],
+    ) {
+        let data = encode_texture_data(image, &GVREncodeOptions::new(format)).unwrap();
+        assert_eq!(data.len(), format.data_size(image.width, image.height));
+
+        let decoded = decode_texture_data(format, &data, image.width, image.height, None).unwrap();
+        for (original, result) in image
+            .pixels
+            .chunks_exact(4)
+            .zip(decoded.pixels.chunks_exact(4))
+        {
+            for &ch in channels {
+                assert!(
+                    original[ch].abs_diff(result[ch]) <= tolerance,
+                    "{format}: {original:?} became {result:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_color_formats() {
+        let image = make_gradient(12, 10, false);
+        assert_round_trip(GVRDataFormat::ARGB8, &image, 0, &[0, 1, 2, 3]);
+        assert_round_trip(GVRDataFormat::RGB565, &image, 4, &[0, 1, 2]);
+        assert_round_trip(GVRDataFormat::RGB5A3, &image, 18, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn round_trip_intensity_formats() {
+        let image = make_gradient(20, 6, true);
+        assert_round_trip(GVRDataFormat::IA8, &image, 0, &[0, 1, 2, 3]);
+        assert_round_trip(GVRDataFormat::IA4, &image, 8, &[0, 1, 2, 3]);
+        assert_round_trip(GVRDataFormat::I8, &image, 0, &[0, 1, 2]);
+        assert_round_trip(GVRDataFormat::I4, &image, 8, &[0, 1, 2]);
+    }
+
+    #[test]
+    fn rgb5a3_mode_selection() {
+        assert_eq!(rgba_to_rgb5a3([0xFF, 0, 0, 0xFF]), 0xFC00);
+        assert_eq!(rgba_to_rgb5a3([0xFF, 0, 0xFF, 0x92]), 0x4F0F);
+    }
+
+    #[test]
+    fn invalid_dimensions() {
+        let options = GVREncodeOptions::new(GVRDataFormat::I8);
+        let err = encode_texture_data(&RGBAImage::new(0, 4), &options).unwrap_err();
+        assert_eq!(
+            err,
+            GVREncodeError::InvalidDimensions {
+                width: 0,
+                height: 4
+            }
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! This module contains the plain RGBA8 image type that GVR textures are decoded into and encoded
+//! from.
+
+/// A plain, uncompressed image with 8 bits per channel in RGBA order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RGBAImage {
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// The pixel data, stored row by row with 4 bytes (R, G, B, A) per pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl RGBAImage {
+    /// Creates a new fully transparent black image with the given dimensions.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Creates a new image from already existing `pixels`.
+    ///
+    /// Returns [`None`] if the length of `pixels` doesn't match the given dimensions.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Gets the color of the pixel at the given coordinates.
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = self.pixel_index(x, y);
+        self.pixels[idx..idx + 4].try_into().unwrap()
+    }
+
+    /// Sets the color of the pixel at the given coordinates.
+    ///
+    /// Panics if the coordinates are out of bounds.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        let idx = self.pixel_index(x, y);
+        self.pixels[idx..idx + 4].copy_from_slice(&color);
+    }
+
+    fn pixel_index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height);
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+}
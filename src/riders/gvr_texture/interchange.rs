@@ -0,0 +1,190 @@
+//! This module contains the functionality to convert GVR textures to and from PNG images, so they
+//! can be edited in other tools. It also contains the error type shared by all the image file
+//! formats GVR textures can be converted to and from.
+
+use std::fmt;
+
+use super::{
+    decode::GVRDecodeError,
+    encode::{GVREncodeError, GVREncodeOptions},
+    image::RGBAImage,
+    GVRTexture,
+};
+
+/// An error that occurred while converting between a GVR texture and an image file.
+#[derive(Debug)]
+pub enum GVRInterchangeError {
+    /// The PNG file couldn't be read.
+    PngDecoding(png::DecodingError),
+    /// The PNG file couldn't be written.
+    PngEncoding(png::EncodingError),
+    /// The DDS file is invalid or uses a pixel format that isn't supported.
+    InvalidDds(String),
+    /// The TGA file is invalid or uses an image type that isn't supported.
+    InvalidTga(String),
+    /// The GVR texture couldn't be decoded.
+    Decode(GVRDecodeError),
+    /// The image couldn't be encoded into a GVR texture.
+    Encode(GVREncodeError),
+}
+
+impl fmt::Display for GVRInterchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVRInterchangeError::PngDecoding(err) => write!(f, "invalid PNG file: {err}"),
+            GVRInterchangeError::PngEncoding(err) => write!(f, "couldn't write PNG file: {err}"),
+            GVRInterchangeError::InvalidDds(reason) => write!(f, "invalid DDS file: {reason}"),
+            GVRInterchangeError::InvalidTga(reason) => write!(f, "invalid TGA file: {reason}"),
+            GVRInterchangeError::Decode(err) => write!(f, "couldn't decode texture: {err}"),
+            GVRInterchangeError::Encode(err) => write!(f, "couldn't encode texture: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GVRInterchangeError {}
+
+impl From<png::DecodingError> for GVRInterchangeError {
+    fn from(err: png::DecodingError) -> Self {
+        GVRInterchangeError::PngDecoding(err)
+    }
+}
+
+impl From<png::EncodingError> for GVRInterchangeError {
+    fn from(err: png::EncodingError) -> Self {
+        GVRInterchangeError::PngEncoding(err)
+    }
+}
+
+impl From<GVRDecodeError> for GVRInterchangeError {
+    fn from(err: GVRDecodeError) -> Self {
+        GVRInterchangeError::Decode(err)
+    }
+}
+
+impl From<GVREncodeError> for GVRInterchangeError {
+    fn from(err: GVREncodeError) -> Self {
+        GVRInterchangeError::Encode(err)
+    }
+}
+
+/// Reads a PNG file from `data` into an [`RGBAImage`].
+///
+/// Every color type and bit depth is supported. Palettes and grayscale are expanded into RGBA,
+/// and 16-bit channels are cut down to 8 bits.
+pub fn read_png(data: &[u8]) -> Result<RGBAImage, GVRInterchangeError> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v, 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xFF])
+            .collect(),
+        png::ColorType::Rgba => buf,
+        // Palettes are always expanded by the transformations above
+        png::ColorType::Indexed => unreachable!(),
+    };
+
+    Ok(RGBAImage::from_pixels(info.width, info.height, pixels).unwrap())
+}
+
+/// Writes the given `image` into a new 8-bit RGBA PNG file.
+pub fn write_png(image: &RGBAImage) -> Result<Vec<u8>, GVRInterchangeError> {
+    let mut buf = vec![];
+
+    let mut encoder = png::Encoder::new(&mut buf, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    writer.finish()?;
+
+    Ok(buf)
+}
+
+impl GVRTexture {
+    /// Encodes the PNG file in `data` into a new GVR texture named `name`, as per the given
+    /// `options`. See [`GVRTexture::encode()`] for more details.
+    pub fn from_png(
+        name: String,
+        data: &[u8],
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVRInterchangeError> {
+        let image = read_png(data)?;
+        Ok(GVRTexture::encode(name, &image, options)?)
+    }
+
+    /// Decodes the base texture of this [`GVRTexture`] and writes it into a new PNG file.
+    pub fn to_png(&self) -> Result<Vec<u8>, GVRInterchangeError> {
+        write_png(&self.decode()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::GVRDataFormat;
+
+    fn make_image() -> RGBAImage {
+        let mut image = RGBAImage::new(8, 8);
+        for y in 0..8 {
+            for x in 0..8 {
+                image.set_pixel(x, y, [(x * 32) as u8, (y * 32) as u8, 0x40, 0xFF]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = make_image();
+        let png = write_png(&image).unwrap();
+
+        assert_eq!(read_png(&png).unwrap(), image);
+    }
+
+    #[test]
+    fn png_color_types_are_expanded() {
+        let mut buf = vec![];
+        let mut encoder = png::Encoder::new(&mut buf, 2, 1);
+        encoder.set_color(png::ColorType::GrayscaleAlpha);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0x10, 0xFF, 0x80, 0x00]).unwrap();
+        writer.finish().unwrap();
+
+        let image = read_png(&buf).unwrap();
+        assert_eq!(
+            image.pixels,
+            vec![0x10, 0x10, 0x10, 0xFF, 0x80, 0x80, 0x80, 0x00]
+        );
+    }
+
+    #[test]
+    fn texture_png_round_trip() {
+        let image = make_image();
+        let png = write_png(&image).unwrap();
+        let options = GVREncodeOptions::new(GVRDataFormat::ARGB8);
+
+        let tex = GVRTexture::from_png("test".into(), &png, &options).unwrap();
+        assert_eq!(tex.header.data_format, GVRDataFormat::ARGB8);
+        assert_eq!(read_png(&tex.to_png().unwrap()).unwrap(), image);
+    }
+
+    #[test]
+    fn invalid_png() {
+        let err = read_png(b"not a png").unwrap_err();
+        assert!(matches!(err, GVRInterchangeError::PngDecoding(_)));
+    }
+}
@@ -0,0 +1,342 @@
+//! This module contains the functionality to generate the mip levels of a GVR texture from its
+//! base image.
+
+use super::image::RGBAImage;
+
+/// The filter used to downscale the base image into the smaller mip levels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum GVRMipmapFilter {
+    /// Averages all the pixels covered by each output pixel. Fast, but slightly blurry.
+    Box,
+    /// Lanczos filter with a radius of 3 lobes. Keeps the smaller levels sharper.
+    #[default]
+    Lanczos3,
+}
+
+impl GVRMipmapFilter {
+    /// Returns the radius of the filter kernel in source pixels, at a scale of 1.
+    fn radius(&self) -> f32 {
+        match self {
+            GVRMipmapFilter::Box => 0.5,
+            GVRMipmapFilter::Lanczos3 => 3.,
+        }
+    }
+
+    /// Evaluates the filter kernel at the distance `t`.
+    fn weight(&self, t: f32) -> f32 {
+        match self {
+            GVRMipmapFilter::Box => {
+                if t.abs() <= 0.5 {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            GVRMipmapFilter::Lanczos3 => {
+                let t = t.abs();
+                if t < f32::EPSILON {
+                    1.
+                } else if t < 3. {
+                    let pi_t = std::f32::consts::PI * t;
+                    3. * pi_t.sin() * (pi_t / 3.).sin() / (pi_t * pi_t)
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+}
+
+/// How many mip levels an encoded texture gets.
+///
+/// A GVR texture with mipmaps always has the full mip chain, as the header can't describe a
+/// shorter one. The GX hardware reads every level down to 1x1 from the texture data.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GVRMipmaps {
+    /// Only the base texture, without any mipmaps.
+    #[default]
+    None,
+    /// The full mip chain, all the way down to 1x1.
+    Full,
+}
+
+impl GVRMipmaps {
+    /// Returns the amount of levels, including the base texture, for a texture with the given
+    /// dimensions.
+    pub fn level_count(&self, width: u32, height: u32) -> usize {
+        match self {
+            GVRMipmaps::None => 1,
+            GVRMipmaps::Full => full_level_count(width, height),
+        }
+    }
+}
+
+/// Returns the amount of levels in the full mip chain of a texture with the given dimensions,
+/// including the base texture.
+pub fn full_level_count(width: u32, height: u32) -> usize {
+    (u32::BITS - width.max(height).leading_zeros()) as usize
+}
+
+/// Completes the mip chain `levels` all the way down to 1x1, generating every missing level
+/// from the smallest level that's given. A chain of only the base texture is kept as is, as
+/// it doesn't need any mipmaps.
+///
+/// See [`generate_mip_levels()`] for what `filter` and `gamma_correct` do.
+pub fn complete_mip_levels(
+    levels: &[RGBAImage],
+    filter: GVRMipmapFilter,
+    gamma_correct: bool,
+) -> Vec<RGBAImage> {
+    let mut levels = levels.to_vec();
+    let (Some(base), Some(smallest)) = (levels.first(), levels.last()) else {
+        return levels;
+    };
+
+    let full = full_level_count(base.width, base.height);
+    if levels.len() > 1 && levels.len() < full {
+        let missing = full - levels.len();
+        let generated = generate_mip_levels(smallest, missing + 1, filter, gamma_correct);
+        levels.extend(generated.into_iter().skip(1));
+    }
+
+    levels.truncate(full.max(1));
+    levels
+}
+
+/// Generates `level_count` mip levels from `image`, with the first one being `image` itself.
+///
+/// Each level halves the dimensions of the previous one, down to a minimum of 1 pixel. Every
+/// level is filtered directly from the base image. Colors are weighted by their alpha while
+/// filtering, so fully transparent pixels don't bleed into their neighbours. If `gamma_correct`
+/// is set, the filtering is done in linear space instead of sRGB.
+pub fn generate_mip_levels(
+    image: &RGBAImage,
+    level_count: usize,
+    filter: GVRMipmapFilter,
+    gamma_correct: bool,
+) -> Vec<RGBAImage> {
+    let mut levels = vec![image.clone()];
+    if level_count <= 1 {
+        return levels;
+    }
+
+    let to_linear = |v: u8| {
+        let v = v as f32 / 255.;
+        if gamma_correct {
+            srgb_to_linear(v)
+        } else {
+            v
+        }
+    };
+
+    // Premultiplied, optionally linear, floating point version of the base image
+    let base: Vec<[f32; 4]> = image
+        .pixels
+        .chunks_exact(4)
+        .map(|p| {
+            let alpha = p[3] as f32 / 255.;
+            [
+                to_linear(p[0]) * alpha,
+                to_linear(p[1]) * alpha,
+                to_linear(p[2]) * alpha,
+                alpha,
+            ]
+        })
+        .collect();
+
+    for level in 1..level_count {
+        let width = (image.width >> level).max(1);
+        let height = (image.height >> level).max(1);
+
+        let horizontal = resample_rows(&base, image.width, image.height, width, filter);
+        let resampled = resample_columns(&horizontal, width, image.height, height, filter);
+
+        let mut mip = RGBAImage::new(width, height);
+        for (out, color) in mip.pixels.chunks_exact_mut(4).zip(resampled) {
+            let alpha = color[3].clamp(0., 1.);
+            for ch in 0..3 {
+                let mut v = if alpha > 0. { color[ch] / alpha } else { 0. };
+                v = v.clamp(0., 1.);
+                if gamma_correct {
+                    v = linear_to_srgb(v);
+                }
+                out[ch] = (v * 255.).round() as u8;
+            }
+            out[3] = (alpha * 255.).round() as u8;
+        }
+
+        levels.push(mip);
+    }
+
+    levels
+}
+
+/// Calculates the source pixel range and normalized weights for every output pixel, when
+/// resampling `src_len` pixels into `dst_len` pixels.
+fn filter_weights(src_len: u32, dst_len: u32, filter: GVRMipmapFilter) -> Vec<(usize, Vec<f32>)> {
+    let scale = (src_len as f32 / dst_len as f32).max(1.);
+    let support = filter.radius() * scale;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * src_len as f32 / dst_len as f32;
+            let start = (center - support).floor().max(0.) as usize;
+            let end = ((center + support).ceil() as usize).min(src_len as usize);
+
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| filter.weight((j as f32 + 0.5 - center) / scale))
+                .collect();
+
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+
+            (start, weights)
+        })
+        .collect()
+}
+
+fn resample_rows(
+    src: &[[f32; 4]],
+    src_width: u32,
+    height: u32,
+    dst_width: u32,
+    filter: GVRMipmapFilter,
+) -> Vec<[f32; 4]> {
+    let weights = filter_weights(src_width, dst_width, filter);
+    let mut dst = Vec::with_capacity((dst_width * height) as usize);
+
+    for row in src.chunks_exact(src_width as usize) {
+        for (start, kernel) in &weights {
+            let mut color = [0.; 4];
+            for (k, w) in kernel.iter().enumerate() {
+                for ch in 0..4 {
+                    color[ch] += row[start + k][ch] * w;
+                }
+            }
+            dst.push(color);
+        }
+    }
+
+    dst
+}
+
+fn resample_columns(
+    src: &[[f32; 4]],
+    width: u32,
+    src_height: u32,
+    dst_height: u32,
+    filter: GVRMipmapFilter,
+) -> Vec<[f32; 4]> {
+    let weights = filter_weights(src_height, dst_height, filter);
+    let width = width as usize;
+    let mut dst = Vec::with_capacity(width * dst_height as usize);
+
+    for (start, kernel) in &weights {
+        for x in 0..width {
+            let mut color = [0.; 4];
+            for (k, w) in kernel.iter().enumerate() {
+                for ch in 0..4 {
+                    color[ch] += src[(start + k) * width + x][ch] * w;
+                }
+            }
+            dst.push(color);
+        }
+    }
+
+    dst
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1. / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a checkerboard of black and white pixels.
+    fn make_checkerboard(size: u32) -> RGBAImage {
+        let mut image = RGBAImage::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let v = if (x + y) % 2 == 0 { 0xFF } else { 0 };
+                image.set_pixel(x, y, [v, v, v, 0xFF]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn level_count() {
+        assert_eq!(GVRMipmaps::None.level_count(64, 64), 1);
+        assert_eq!(GVRMipmaps::Full.level_count(64, 16), 7);
+        assert_eq!(full_level_count(8, 1), 4);
+    }
+
+    #[test]
+    fn complete_partial_chain() {
+        let base = make_checkerboard(8);
+        let partial = generate_mip_levels(&base, 2, GVRMipmapFilter::Box, false);
+
+        let levels = complete_mip_levels(&partial, GVRMipmapFilter::Box, false);
+        let sizes: Vec<(u32, u32)> = levels.iter().map(|l| (l.width, l.height)).collect();
+        assert_eq!(sizes, [(8, 8), (4, 4), (2, 2), (1, 1)]);
+        assert_eq!(levels[1], partial[1]);
+
+        // Only the base level means no mipmaps at all
+        assert_eq!(
+            complete_mip_levels(&partial[..1], GVRMipmapFilter::Box, false).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn box_filter_averages() {
+        let levels = generate_mip_levels(&make_checkerboard(8), 4, GVRMipmapFilter::Box, false);
+
+        assert_eq!(levels.len(), 4);
+        assert_eq!((levels[3].width, levels[3].height), (1, 1));
+        for level in &levels[1..] {
+            assert!(level
+                .pixels
+                .chunks_exact(4)
+                .all(|p| p == [0x80, 0x80, 0x80, 0xFF]));
+        }
+    }
+
+    #[test]
+    fn gamma_correct_averages() {
+        let levels = generate_mip_levels(&make_checkerboard(2), 2, GVRMipmapFilter::Box, true);
+
+        // Half of linear white is about 188 in sRGB
+        assert_eq!(levels[1].get_pixel(0, 0), [188, 188, 188, 0xFF]);
+    }
+
+    #[test]
+    fn transparent_pixels_dont_bleed() {
+        let mut image = RGBAImage::new(2, 2);
+        image.set_pixel(0, 0, [0xFF, 0, 0, 0xFF]);
+
+        for filter in [GVRMipmapFilter::Box, GVRMipmapFilter::Lanczos3] {
+            let levels = generate_mip_levels(&image, 2, filter, false);
+            let [r, g, b, a] = levels[1].get_pixel(0, 0);
+
+            assert_eq!((r, g, b), (0xFF, 0, 0));
+            assert!(a > 0 && a < 0xFF);
+        }
+    }
+}
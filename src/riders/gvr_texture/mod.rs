@@ -0,0 +1,1441 @@
+//! This module contains all the functionality to work with buffers of data that are GVR textures.
+
+use std::{
+    fmt,
+    io::{Cursor, Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use decode::GVRDecodeError;
+use encode::{GVREncodeError, GVREncodeOptions, GVRPalettePlacement};
+use image::RGBAImage;
+use interchange::GVRInterchangeError;
+use palette::GVRPalette;
+
+mod cmpr;
+pub mod constraints;
+pub mod dds;
+pub mod decode;
+pub mod diff;
+pub mod dolphin;
+pub mod encode;
+pub mod image;
+pub mod interchange;
+pub mod mipmap;
+pub mod palette;
+mod quantize;
+pub mod tga;
+
+/// Offset of the size of the global index header, relative to the start of the header.
+const GLOBAL_INDEX_HEADER_SIZE_OFFSET: u64 = 0x04;
+/// Offset of the global index, relative to the start of the global index header.
+const GLOBAL_INDEX_OFFSET: u64 = 0x08;
+/// Offset of the chunk size, relative to the start of the "GVRT" chunk.
+const GVRT_SIZE_OFFSET: u64 = 0x04;
+/// Offset of the byte containing the palette format and the data flags, relative to the start of
+/// the "GVRT" chunk.
+const FORMAT_FLAGS_OFFSET: u64 = 0x0A;
+/// Offset of the data format, relative to the start of the "GVRT" chunk.
+const DATA_FORMAT_OFFSET: u64 = 0x0B;
+/// Offset of the texture width, relative to the start of the "GVRT" chunk.
+const WIDTH_OFFSET: u64 = 0x0C;
+/// Offset of the texture height, relative to the start of the "GVRT" chunk.
+const HEIGHT_OFFSET: u64 = 0x0E;
+/// The size of the magic and size fields of a chunk, which the chunk size doesn't include.
+const CHUNK_HEADER_SIZE: u64 = 0x08;
+
+/// The size of the full GVR header (GCIX + GVRT headers) in bytes. Texture data starts right
+/// after it.
+pub const GVR_HEADER_SIZE: u32 = 0x20;
+
+/// The pixel format of the texture data in a GVR texture.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum GVRDataFormat {
+    /// 4-bit intensity.
+    I4,
+    /// 8-bit intensity.
+    I8,
+    /// 4-bit intensity with 4-bit alpha.
+    IA4,
+    /// 8-bit intensity with 8-bit alpha.
+    IA8,
+    /// 16-bit color without alpha.
+    RGB565,
+    /// 16-bit color, either with 3-bit alpha or without alpha.
+    RGB5A3,
+    /// 32-bit color with 8-bit alpha.
+    ARGB8,
+    /// 4-bit palette indices.
+    C4,
+    /// 8-bit palette indices.
+    C8,
+    /// 14-bit palette indices.
+    C14X2,
+    /// S3TC/DXT1 compressed color.
+    #[default]
+    CMPR,
+}
+
+impl GVRDataFormat {
+    /// Returns the format code that's stored in the GVR header for this format.
+    pub fn code(&self) -> u8 {
+        match self {
+            GVRDataFormat::I4 => 0x0,
+            GVRDataFormat::I8 => 0x1,
+            GVRDataFormat::IA4 => 0x2,
+            GVRDataFormat::IA8 => 0x3,
+            GVRDataFormat::RGB565 => 0x4,
+            GVRDataFormat::RGB5A3 => 0x5,
+            GVRDataFormat::ARGB8 => 0x6,
+            GVRDataFormat::C4 => 0x8,
+            GVRDataFormat::C8 => 0x9,
+            GVRDataFormat::C14X2 => 0xA,
+            GVRDataFormat::CMPR => 0xE,
+        }
+    }
+
+    /// Returns the format matching the given format `code` from a GVR header, or [`None`] if the
+    /// code is unknown.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x0 => Some(GVRDataFormat::I4),
+            0x1 => Some(GVRDataFormat::I8),
+            0x2 => Some(GVRDataFormat::IA4),
+            0x3 => Some(GVRDataFormat::IA8),
+            0x4 => Some(GVRDataFormat::RGB565),
+            0x5 => Some(GVRDataFormat::RGB5A3),
+            0x6 => Some(GVRDataFormat::ARGB8),
+            0x8 => Some(GVRDataFormat::C4),
+            0x9 => Some(GVRDataFormat::C8),
+            0xA => Some(GVRDataFormat::C14X2),
+            0xE => Some(GVRDataFormat::CMPR),
+            _ => None,
+        }
+    }
+
+    /// Returns the width and height of a single block (tile) in pixels for this format.
+    ///
+    /// The texture data is stored block by block, left to right and top to bottom, with the
+    /// pixels of each block stored in row-major order.
+    pub fn block_dimensions(&self) -> (u32, u32) {
+        match self {
+            GVRDataFormat::I4 | GVRDataFormat::C4 | GVRDataFormat::CMPR => (8, 8),
+            GVRDataFormat::I8 | GVRDataFormat::IA4 | GVRDataFormat::C8 => (8, 4),
+            GVRDataFormat::IA8
+            | GVRDataFormat::RGB565
+            | GVRDataFormat::RGB5A3
+            | GVRDataFormat::ARGB8
+            | GVRDataFormat::C14X2 => (4, 4),
+        }
+    }
+
+    /// Returns the amount of bits a single pixel takes up in this format.
+    pub fn bits_per_pixel(&self) -> u32 {
+        match self {
+            GVRDataFormat::I4 | GVRDataFormat::C4 | GVRDataFormat::CMPR => 4,
+            GVRDataFormat::I8 | GVRDataFormat::IA4 | GVRDataFormat::C8 => 8,
+            GVRDataFormat::IA8
+            | GVRDataFormat::RGB565
+            | GVRDataFormat::RGB5A3
+            | GVRDataFormat::C14X2 => 16,
+            GVRDataFormat::ARGB8 => 32,
+        }
+    }
+
+    /// Returns the size of a single block in bytes for this format.
+    pub fn block_size(&self) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+        (block_width * block_height * self.bits_per_pixel() / 8) as usize
+    }
+
+    /// Calculates the size in bytes of the texture data of a texture with the given dimensions.
+    ///
+    /// Textures always take up whole blocks, even if the dimensions aren't a multiple of the
+    /// block dimensions.
+    pub fn data_size(&self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+        let block_count = width.div_ceil(block_width) * height.div_ceil(block_height);
+        block_count as usize * self.block_size()
+    }
+
+    /// Whether this format stores palette indices instead of colors.
+    pub fn is_palettized(&self) -> bool {
+        matches!(
+            self,
+            GVRDataFormat::C4 | GVRDataFormat::C8 | GVRDataFormat::C14X2
+        )
+    }
+
+    /// Whether images can be encoded into this format via [`GVRTexture::encode()`].
+    pub fn is_encodable(&self) -> bool {
+        *self != GVRDataFormat::C14X2
+    }
+}
+
+/// The pixel format of the palette entries of a palettized GVR texture.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumIter)]
+pub enum GVRPaletteFormat {
+    /// 8-bit intensity with 8-bit alpha.
+    #[default]
+    IA8,
+    /// 16-bit color without alpha.
+    RGB565,
+    /// 16-bit color, either with 3-bit alpha or without alpha.
+    RGB5A3,
+}
+
+impl GVRPaletteFormat {
+    /// Returns the format code that's stored in the GVR header for this format.
+    pub fn code(&self) -> u8 {
+        match self {
+            GVRPaletteFormat::IA8 => 0x0,
+            GVRPaletteFormat::RGB565 => 0x1,
+            GVRPaletteFormat::RGB5A3 => 0x2,
+        }
+    }
+
+    /// Returns the format matching the given format `code` from a GVR header, or [`None`] if the
+    /// code is unknown.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x0 => Some(GVRPaletteFormat::IA8),
+            0x1 => Some(GVRPaletteFormat::RGB565),
+            0x2 => Some(GVRPaletteFormat::RGB5A3),
+            _ => None,
+        }
+    }
+}
+
+/// The flag indicating that the texture contains mipmaps.
+pub const GVR_FLAG_MIPMAPS: u8 = 0x1;
+/// The flag indicating that the texture uses a palette from a separate GVPL file.
+pub const GVR_FLAG_EXTERNAL_PALETTE: u8 = 0x2;
+/// The flag indicating that the texture has its palette embedded right before the texture data.
+pub const GVR_FLAG_INTERNAL_PALETTE: u8 = 0x8;
+
+/// The parsed header of a GVR texture.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GVRHeader {
+    /// The global index of the texture, stored in the GCIX header.
+    pub global_index: u32,
+    /// The format of the palette entries. Only meaningful for palettized textures.
+    pub palette_format: GVRPaletteFormat,
+    /// The data flags of the texture. See the `GVR_FLAG_*` constants.
+    pub flags: u8,
+    /// The format of the texture data.
+    pub data_format: GVRDataFormat,
+    /// The width of the texture in pixels.
+    pub width: u16,
+    /// The height of the texture in pixels.
+    pub height: u16,
+}
+
+impl GVRHeader {
+    /// Parses the header of the GVR texture in `reader`. Every [`GVRVariant`] of GVR texture
+    /// files is supported.
+    ///
+    /// This assumes that the `reader` is at the very start of the file!
+    /// If the header is valid, the `reader` position is returned back to the start.
+    /// Otherwise the `reader` position will be altered when this function returns.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, GVRError> {
+        let start_pos = reader
+            .stream_position()
+            .map_err(|_| GVRError::truncated("magic", 0))?;
+
+        let layout = GVRChunkLayout::read(reader)?;
+        let gvrt_offset = layout.gvrt_offset;
+
+        let truncated = |field, offset| GVRError::truncated(field, gvrt_offset + offset);
+
+        reader
+            .seek(SeekFrom::Start(
+                start_pos + gvrt_offset + FORMAT_FLAGS_OFFSET,
+            ))
+            .map_err(|_| truncated("palette format/flags", FORMAT_FLAGS_OFFSET))?;
+        let format_flags = reader
+            .read_u8()
+            .map_err(|_| truncated("palette format/flags", FORMAT_FLAGS_OFFSET))?;
+        let data_format_code = reader
+            .read_u8()
+            .map_err(|_| truncated("data format", DATA_FORMAT_OFFSET))?;
+        let width = reader
+            .read_u16::<BigEndian>()
+            .map_err(|_| truncated("width", WIDTH_OFFSET))?;
+        let height = reader
+            .read_u16::<BigEndian>()
+            .map_err(|_| truncated("height", HEIGHT_OFFSET))?;
+
+        let data_format =
+            GVRDataFormat::from_code(data_format_code).ok_or(GVRError::UnsupportedFormat {
+                field: "data format",
+                offset: gvrt_offset + DATA_FORMAT_OFFSET,
+                code: data_format_code,
+            })?;
+
+        // The palette format nibble is only meaningful for palettized textures, so garbage in it
+        // is tolerated otherwise.
+        let palette_format_code = format_flags >> 4;
+        let palette_format = match GVRPaletteFormat::from_code(palette_format_code) {
+            Some(format) => format,
+            None if !data_format.is_palettized() => GVRPaletteFormat::default(),
+            None => {
+                return Err(GVRError::UnsupportedFormat {
+                    field: "palette format",
+                    offset: gvrt_offset + FORMAT_FLAGS_OFFSET,
+                    code: palette_format_code,
+                })
+            }
+        };
+
+        if width == 0 {
+            return Err(GVRError::BadValue {
+                field: "width",
+                offset: gvrt_offset + WIDTH_OFFSET,
+                value: 0,
+            });
+        }
+
+        if height == 0 {
+            return Err(GVRError::BadValue {
+                field: "height",
+                offset: gvrt_offset + HEIGHT_OFFSET,
+                value: 0,
+            });
+        }
+
+        // Return reader back to original position
+        reader
+            .seek(SeekFrom::Start(start_pos))
+            .map_err(|_| GVRError::truncated("magic", 0))?;
+
+        Ok(Self {
+            global_index: layout.global_index,
+            palette_format,
+            flags: format_flags & 0xF,
+            data_format,
+            width,
+            height,
+        })
+    }
+
+    /// Serializes this header into the full 0x20 byte GCIX + GVRT header of a texture, where
+    /// `data_size` is the size of everything following the header (palette and texture data).
+    pub fn to_bytes(&self, data_size: usize) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(GVR_HEADER_SIZE as usize);
+        write_gcix_header(&mut buf, self.global_index);
+
+        // The size counts everything after the size field itself
+        buf.extend_from_slice(b"GVRT");
+        let gvrt_size = GVR_HEADER_SIZE as usize - 0x18 + data_size;
+        buf.write_u32::<LittleEndian>(gvrt_size as u32).unwrap();
+        buf.write_u16::<BigEndian>(0).unwrap();
+        buf.write_u8((self.palette_format.code() << 4) | (self.flags & 0xF))
+            .unwrap();
+        buf.write_u8(self.data_format.code()).unwrap();
+        buf.write_u16::<BigEndian>(self.width).unwrap();
+        buf.write_u16::<BigEndian>(self.height).unwrap();
+
+        buf
+    }
+
+    /// Whether the texture contains mipmaps after the base texture.
+    pub fn has_mipmaps(&self) -> bool {
+        self.flags & GVR_FLAG_MIPMAPS != 0
+    }
+
+    /// Whether the texture has its palette embedded before the texture data.
+    pub fn has_internal_palette(&self) -> bool {
+        self.flags & GVR_FLAG_INTERNAL_PALETTE != 0
+    }
+
+    /// Whether the texture relies on a palette from a separate GVPL file.
+    pub fn has_external_palette(&self) -> bool {
+        self.flags & GVR_FLAG_EXTERNAL_PALETTE != 0
+    }
+
+    /// Returns the amount of entries in the palette embedded in the texture, or 0 if the texture
+    /// doesn't have one.
+    ///
+    /// Only C4 and C8 textures can have an embedded palette.
+    pub fn internal_palette_entry_count(&self) -> usize {
+        if !self.has_internal_palette() {
+            return 0;
+        }
+
+        match self.data_format {
+            GVRDataFormat::C4 => 16,
+            GVRDataFormat::C8 => 256,
+            _ => 0,
+        }
+    }
+
+    /// Returns the offset of the texture data, relative to the start of the texture. This skips
+    /// the embedded palette if there is one.
+    pub fn data_offset(&self) -> usize {
+        GVR_HEADER_SIZE as usize + self.internal_palette_entry_count() * 2
+    }
+
+    /// Returns the amount of mip levels (including the base texture) this header describes.
+    ///
+    /// Mipmapped textures have levels all the way down to 1x1, halving both dimensions on each
+    /// level.
+    pub fn mip_level_count(&self) -> usize {
+        if !self.has_mipmaps() {
+            return 1;
+        }
+
+        let largest = self.width.max(self.height);
+        (u16::BITS - largest.leading_zeros()) as usize
+    }
+
+    /// Returns the layout of every mip level this header describes, starting with the base
+    /// texture.
+    pub fn mip_levels(&self) -> Vec<GVRMipLevel> {
+        let mut levels = Vec::with_capacity(self.mip_level_count());
+        let mut offset = self.data_offset();
+
+        for level in 0..self.mip_level_count() {
+            let width = (u32::from(self.width) >> level).max(1);
+            let height = (u32::from(self.height) >> level).max(1);
+            let size = self.data_format.data_size(width, height);
+
+            levels.push(GVRMipLevel {
+                level,
+                width,
+                height,
+                offset,
+                size,
+            });
+            offset += size;
+        }
+
+        levels
+    }
+}
+
+/// Writes the 0x10 byte "GCIX" global index header with the given `global_index` into `buf`.
+pub(super) fn write_gcix_header(buf: &mut Vec<u8>, global_index: u32) {
+    buf.extend_from_slice(b"GCIX");
+    buf.write_u32::<LittleEndian>(0x8).unwrap();
+    buf.write_u32::<BigEndian>(global_index).unwrap();
+    buf.write_u32::<BigEndian>(0).unwrap();
+}
+
+/// The different kinds of headers GVR texture files come with.
+///
+/// Sonic Riders only uses GCIX-headed textures, but other games and community tools also
+/// produce the other variants. Textures are always stored in the GCIX variant once they're read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum GVRVariant {
+    /// A "GCIX" global index header, followed by the "GVRT" chunk.
+    #[default]
+    #[strum(to_string = "GCIX-headed")]
+    GCIX,
+    /// A "GBIX" global index header, followed by the "GVRT" chunk.
+    #[strum(to_string = "GBIX-headed")]
+    GBIX,
+    /// A bare "GVRT" chunk, without a global index header.
+    #[strum(to_string = "headerless")]
+    Headerless,
+}
+
+/// Describes where the "GVRT" chunk of a GVR texture file is.
+struct GVRChunkLayout {
+    /// The kind of header the file has.
+    variant: GVRVariant,
+    /// The global index from the global index header, or 0 if there's no such header.
+    global_index: u32,
+    /// Offset of the "GVRT" chunk, relative to the start of the file.
+    gvrt_offset: u64,
+    /// The size of the "GVRT" chunk, including its magic and size fields.
+    gvrt_size: u64,
+}
+
+impl GVRChunkLayout {
+    /// Finds the "GVRT" chunk of the GVR texture file in `reader`, checking the magic of every
+    /// header on the way.
+    ///
+    /// This assumes that the `reader` is at the very start of the file!
+    /// If the layout is valid, the `reader` position is returned back to the start.
+    /// Otherwise the `reader` position will be altered when this function returns.
+    fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, GVRError> {
+        let truncated = GVRError::truncated;
+        let start_pos = reader
+            .stream_position()
+            .map_err(|_| truncated("magic", 0))?;
+
+        let mut magic = [0; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| truncated("magic", 0))?;
+
+        let variant = match &magic {
+            b"GCIX" => GVRVariant::GCIX,
+            b"GBIX" => GVRVariant::GBIX,
+            b"GVRT" => GVRVariant::Headerless,
+            _ => {
+                return Err(GVRError::BadMagic {
+                    field: "magic",
+                    offset: 0,
+                    found: magic,
+                })
+            }
+        };
+
+        let mut global_index = 0;
+        let mut gvrt_offset = 0;
+        if variant != GVRVariant::Headerless {
+            let header_size = reader.read_u32::<LittleEndian>().map_err(|_| {
+                truncated("global index header size", GLOBAL_INDEX_HEADER_SIZE_OFFSET)
+            })?;
+            global_index = reader
+                .read_u32::<BigEndian>()
+                .map_err(|_| truncated("global index", GLOBAL_INDEX_OFFSET))?;
+
+            gvrt_offset = CHUNK_HEADER_SIZE + u64::from(header_size);
+            reader
+                .seek(SeekFrom::Start(start_pos + gvrt_offset))
+                .map_err(|_| truncated("GVRT magic", gvrt_offset))?;
+            reader
+                .read_exact(&mut magic)
+                .map_err(|_| truncated("GVRT magic", gvrt_offset))?;
+
+            if &magic != b"GVRT" {
+                return Err(GVRError::BadMagic {
+                    field: "GVRT magic",
+                    offset: gvrt_offset,
+                    found: magic,
+                });
+            }
+        }
+
+        let gvrt_size = reader
+            .read_u32::<LittleEndian>()
+            .map_err(|_| truncated("GVRT size", gvrt_offset + GVRT_SIZE_OFFSET))?;
+
+        // Return reader back to original position
+        reader
+            .seek(SeekFrom::Start(start_pos))
+            .map_err(|_| truncated("magic", 0))?;
+
+        Ok(Self {
+            variant,
+            global_index,
+            gvrt_offset,
+            gvrt_size: CHUNK_HEADER_SIZE + u64::from(gvrt_size),
+        })
+    }
+}
+
+/// Describes the location and dimensions of a single mip level in a GVR texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GVRMipLevel {
+    /// The index of the mip level, where 0 is the base texture.
+    pub level: usize,
+    /// The width of the mip level in pixels.
+    pub width: u32,
+    /// The height of the mip level in pixels.
+    pub height: u32,
+    /// The offset of the mip level data, relative to the start of the texture.
+    pub offset: usize,
+    /// The size of the mip level data in bytes.
+    pub size: usize,
+}
+
+impl GVRMipLevel {
+    /// Returns the byte range of the mip level data, relative to the start of the texture.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// An error that occurred while parsing a GVR texture or a GVPL palette.
+///
+/// Every offset is relative to the start of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GVRError {
+    /// A magic didn't match.
+    BadMagic {
+        /// The name of the magic.
+        field: &'static str,
+        /// The offset of the magic.
+        offset: u64,
+        /// The bytes that were found instead.
+        found: [u8; 4],
+    },
+    /// The buffer ended before a header field could be read.
+    TruncatedHeader {
+        /// The name of the field.
+        field: &'static str,
+        /// The offset of the field.
+        offset: u64,
+    },
+    /// A size field says that the data is bigger than what's left of the buffer.
+    SizeExceedsBuffer {
+        /// The name of the size field.
+        field: &'static str,
+        /// The offset of the size field.
+        offset: u64,
+        /// The size in bytes the size field describes.
+        size: u64,
+        /// The amount of bytes that are actually available.
+        available: u64,
+    },
+    /// A format code doesn't match any known format.
+    UnsupportedFormat {
+        /// The name of the format field.
+        field: &'static str,
+        /// The offset of the format field.
+        offset: u64,
+        /// The format code that was found.
+        code: u8,
+    },
+    /// A header field contains a value that isn't valid for it.
+    BadValue {
+        /// The name of the field.
+        field: &'static str,
+        /// The offset of the field.
+        offset: u64,
+        /// The value that was found.
+        value: u32,
+    },
+}
+
+impl GVRError {
+    fn truncated(field: &'static str, offset: u64) -> Self {
+        GVRError::TruncatedHeader { field, offset }
+    }
+
+    /// Returns the name of the field the error is about.
+    pub fn field(&self) -> &'static str {
+        match self {
+            GVRError::BadMagic { field, .. }
+            | GVRError::TruncatedHeader { field, .. }
+            | GVRError::SizeExceedsBuffer { field, .. }
+            | GVRError::UnsupportedFormat { field, .. }
+            | GVRError::BadValue { field, .. } => field,
+        }
+    }
+
+    /// Returns the offset of the field the error is about, relative to the start of the file.
+    pub fn offset(&self) -> u64 {
+        match self {
+            GVRError::BadMagic { offset, .. }
+            | GVRError::TruncatedHeader { offset, .. }
+            | GVRError::SizeExceedsBuffer { offset, .. }
+            | GVRError::UnsupportedFormat { offset, .. }
+            | GVRError::BadValue { offset, .. } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for GVRError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVRError::BadMagic {
+                field,
+                offset,
+                found,
+            } => write!(
+                f,
+                "invalid {field} at offset {offset:#x}, found {:?}",
+                String::from_utf8_lossy(found)
+            ),
+            GVRError::TruncatedHeader { field, offset } => write!(
+                f,
+                "header is truncated at the {field} field (offset {offset:#x})"
+            ),
+            GVRError::SizeExceedsBuffer {
+                field,
+                offset,
+                size,
+                available,
+            } => write!(
+                f,
+                "{field} at offset {offset:#x} is {size:#x} bytes, but only {available:#x} bytes are available"
+            ),
+            GVRError::UnsupportedFormat {
+                field,
+                offset,
+                code,
+            } => write!(f, "unsupported {field} {code:#x} at offset {offset:#x}"),
+            GVRError::BadValue {
+                field,
+                offset,
+                value,
+            } => write!(f, "invalid {field} value {value:#x} at offset {offset:#x}"),
+        }
+    }
+}
+
+impl std::error::Error for GVRError {}
+
+/// Represents a buffer of data that is a GVR texture.
+///
+/// It's possible that when first constructed, it may not be a GVR texture.
+/// This should be double-checked via [`GVRTexture::validate()`].
+#[derive(Default, Clone)]
+pub struct GVRTexture {
+    /// Name of the texture file. This only contains the name and not the file extension.
+    pub name: String,
+    /// The full size of the texture in bytes.
+    pub size: u32,
+    /// The parsed header of the texture.
+    pub header: GVRHeader,
+    /// The texture data.
+    pub data: Cursor<Vec<u8>>,
+    /// The palette from a separate GVPL file, used when decoding palettized textures that don't
+    /// have an embedded palette. Set this manually before decoding, if needed.
+    pub external_palette: Option<GVRPalette>,
+    /// The kind of header the texture file had when it was read. [`GVRTexture::data`] always
+    /// uses the GCIX variant, regardless of this.
+    pub source_variant: GVRVariant,
+}
+
+impl GVRTexture {
+    /// Constructs a new [`GVRTexture`] in a simple manner from the given `data` with a predefined `size`
+    /// and `header`, and a `name` to represent the name of the texture file.
+    ///
+    /// This already assumes that this is a valid GVR texture, as it doesn't perform any checks,
+    /// and simply makes a new [`GVRTexture`].
+    pub fn new(name: String, size: u32, header: GVRHeader, data: Cursor<Vec<u8>>) -> Self {
+        Self {
+            name,
+            size,
+            header,
+            data,
+            external_palette: None,
+            source_variant: GVRVariant::default(),
+        }
+    }
+
+    /// Constructs a new [`GVRTexture`] from the given `reader` and a `name` to represent the name
+    /// of the texture file.
+    ///
+    /// This function should be used for when you're trying to attempt to make a valid [`GVRTexture`]
+    /// for the first time, as it performs checks to see if it's a valid GVR texture file, and also
+    /// calculates the size of the texture file in full.
+    ///
+    /// Every [`GVRVariant`] is accepted, and normalized into a GCIX-headed texture. Anything after
+    /// the "GVRT" chunk, like padding or extra chunks, is left out. The variant that was read is
+    /// kept in [`GVRTexture::source_variant`].
+    ///
+    /// This assumes that the `reader` is at the very start of the file!
+    /// If it's a valid GVR texture, the `reader` is moved to the end of the texture in the file.
+    /// Otherwise the `reader` position will be altered when this function returns.
+    pub fn new_from_reader<R: Read + Seek>(name: String, reader: &mut R) -> Result<Self, GVRError> {
+        let header = GVRHeader::read(reader)?;
+        let layout = GVRChunkLayout::read(reader)?;
+        let tex_size = layout.gvrt_offset + layout.gvrt_size;
+
+        // Read whole texture into buffer. This is done without allocating the whole size up front,
+        // so a bogus size field can't cause a huge allocation. A read error is handled the same
+        // way as the buffer ending early.
+        let mut buf = vec![];
+        let _ = reader.take(tex_size).read_to_end(&mut buf);
+
+        if (buf.len() as u64) < tex_size {
+            return Err(GVRError::SizeExceedsBuffer {
+                field: "GVRT size",
+                offset: layout.gvrt_offset + GVRT_SIZE_OFFSET,
+                size: layout.gvrt_size - CHUNK_HEADER_SIZE,
+                available: (buf.len() as u64)
+                    .saturating_sub(layout.gvrt_offset + CHUNK_HEADER_SIZE),
+            });
+        }
+
+        // Swap out whatever comes before the "GVRT" chunk for a GCIX header
+        if layout.variant != GVRVariant::GCIX {
+            let mut normalized = Vec::with_capacity(0x10 + layout.gvrt_size as usize);
+            write_gcix_header(&mut normalized, header.global_index);
+            normalized.extend_from_slice(&buf[layout.gvrt_offset as usize..]);
+            buf = normalized;
+        }
+
+        // Return texture with a cursor containing just the texture
+        let mut tex = GVRTexture::new(name, buf.len() as u32, header, Cursor::new(buf));
+        tex.source_variant = layout.variant;
+        Ok(tex)
+    }
+
+    /// Constructs a new [`GVRTexture`] from the GVR texture file at the start of `data`, and a
+    /// `name` to represent the name of the texture file. See [`GVRTexture::new_from_reader()`]
+    /// for more details.
+    ///
+    /// Only the texture itself is copied out of `data`, so this can be used to parse textures
+    /// straight out of a bigger buffer, like the contents of a PackMan archive file.
+    pub fn new_from_bytes(name: String, data: &[u8]) -> Result<Self, GVRError> {
+        GVRTexture::new_from_reader(name, &mut Cursor::new(data))
+    }
+
+    /// Encodes the given `image` into a new GVR texture (including the GCIX and GVRT headers),
+    /// named `name`, as per the given `options`.
+    ///
+    /// For palettized formats, the palette is either embedded into the texture, or stored in
+    /// [`GVRTexture::external_palette`] if it's meant for a separate GVPL file. All mip levels
+    /// share the palette built from the base image.
+    pub fn encode(
+        name: String,
+        image: &RGBAImage,
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVREncodeError> {
+        let level_count = options.mipmaps.level_count(image.width, image.height);
+        let levels = mipmap::generate_mip_levels(
+            image,
+            level_count,
+            options.mipmap_filter,
+            options.gamma_correct_mipmaps,
+        );
+
+        GVRTexture::encode_mip_levels(name, &levels, options)
+    }
+
+    /// Encodes already existing mip `levels` into a new GVR texture, named `name`, as per the
+    /// given `options`. The first level is the base texture, and every level after it has to be
+    /// half the size of the previous one. [`GVREncodeOptions::mipmaps`] is ignored.
+    ///
+    /// If there's more than one level, but not the full mip chain, the missing levels are
+    /// generated as per [`GVREncodeOptions::mipmap_filter`], as the header always describes the
+    /// full chain. See [`mipmap::complete_mip_levels()`] for more details.
+    ///
+    /// See [`GVRTexture::encode()`] for more details.
+    pub fn encode_mip_levels(
+        name: String,
+        levels: &[RGBAImage],
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVREncodeError> {
+        let Some(image) = levels.first() else {
+            return Err(GVREncodeError::InvalidDimensions {
+                width: 0,
+                height: 0,
+            });
+        };
+
+        for (i, level) in levels.iter().enumerate() {
+            let width = (image.width >> i).max(1);
+            let height = (image.height >> i).max(1);
+            if (level.width, level.height) != (width, height) {
+                return Err(GVREncodeError::InvalidDimensions {
+                    width: level.width,
+                    height: level.height,
+                });
+            }
+        }
+
+        let levels = mipmap::complete_mip_levels(
+            levels,
+            options.mipmap_filter,
+            options.gamma_correct_mipmaps,
+        );
+
+        let mut header = GVRHeader {
+            global_index: options.global_index,
+            data_format: options.format,
+            width: image.width as u16,
+            height: image.height as u16,
+            ..Default::default()
+        };
+
+        if levels.len() > 1 {
+            header.flags |= GVR_FLAG_MIPMAPS;
+        }
+
+        let mut palette = None;
+        let mut tex_data = vec![];
+        if options.format.is_palettized() {
+            let (tex_palette, base_data) = encode::encode_palettized_texture_data(image, options)?;
+            tex_data.extend_from_slice(&base_data);
+
+            for level in &levels[1..] {
+                let level_data = encode::encode_with_palette(level, &tex_palette, options)?;
+                tex_data.extend_from_slice(&level_data);
+            }
+
+            header.palette_format = tex_palette.format;
+            palette = Some(tex_palette);
+        } else {
+            for level in &levels {
+                tex_data.extend_from_slice(&encode::encode_texture_data(level, options)?);
+            }
+        }
+
+        let mut external_palette = None;
+        let mut body = vec![];
+        match (palette, options.palette_placement) {
+            (Some(palette), GVRPalettePlacement::Embedded) => {
+                header.flags |= GVR_FLAG_INTERNAL_PALETTE;
+                body.extend_from_slice(&palette.entry_bytes());
+            }
+            (Some(palette), GVRPalettePlacement::External) => {
+                header.flags |= GVR_FLAG_EXTERNAL_PALETTE;
+                external_palette = Some(palette);
+            }
+            (None, _) => {}
+        }
+        body.extend_from_slice(&tex_data);
+
+        let mut tex = GVRTexture::from_header_and_body(name, header, &body);
+        tex.external_palette = external_palette;
+        Ok(tex)
+    }
+
+    /// Re-encodes this texture into a new texture with the same name, as per the given `options`.
+    /// Every mip level that's present is decoded and encoded again as is, so custom mip levels
+    /// are kept and [`GVREncodeOptions::mipmaps`] is ignored.
+    ///
+    /// To convert a texture into another format while keeping everything else the same, use
+    /// [`GVREncodeOptions::matching()`] with a different [`GVREncodeOptions::format`].
+    pub fn convert(&self, options: &GVREncodeOptions) -> Result<Self, GVRInterchangeError> {
+        let levels = (0..self.mip_levels().len().max(1))
+            .map(|level| self.decode_mip_level(level))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GVRTexture::encode_mip_levels(
+            self.name.clone(),
+            &levels,
+            options,
+        )?)
+    }
+
+    /// Builds a new [`GVRTexture`] out of its `header` and `body`, which is everything that
+    /// follows the header (palette and texture data).
+    fn from_header_and_body(name: String, header: GVRHeader, body: &[u8]) -> Self {
+        let mut buf = header.to_bytes(body.len());
+        buf.extend_from_slice(body);
+
+        GVRTexture::new(name, buf.len() as u32, header, Cursor::new(buf))
+    }
+
+    /// Reads the palette embedded in this texture, if it has one.
+    pub fn internal_palette(&self) -> Option<GVRPalette> {
+        let entry_count = self.header.internal_palette_entry_count();
+        if entry_count == 0 {
+            return None;
+        }
+
+        let data = self.data.get_ref().get(GVR_HEADER_SIZE as usize..)?;
+        GVRPalette::from_entry_data(self.header.palette_format, data, entry_count)
+    }
+
+    /// Returns the palette that's used for decoding this texture. The embedded palette takes
+    /// priority over [`GVRTexture::external_palette`].
+    pub fn palette(&self) -> Option<GVRPalette> {
+        self.internal_palette()
+            .or_else(|| self.external_palette.clone())
+    }
+
+    /// Returns all the mip levels that are actually present in the texture data, starting with
+    /// the base texture.
+    ///
+    /// Levels that the header describes, but don't fit into the texture data, are left out.
+    pub fn mip_levels(&self) -> Vec<GVRMipLevel> {
+        let data_len = self.data.get_ref().len();
+
+        self.header
+            .mip_levels()
+            .into_iter()
+            .take_while(|level| level.range().end <= data_len)
+            .collect()
+    }
+
+    /// Returns the raw texture data of the given mip `level`, if it's present.
+    pub fn mip_level_data(&self, level: usize) -> Option<&[u8]> {
+        let mip_level = self.mip_levels().into_iter().nth(level)?;
+        Some(&self.data.get_ref()[mip_level.range()])
+    }
+
+    /// Decodes the base texture of this [`GVRTexture`] into an [`RGBAImage`].
+    ///
+    /// Palettized textures are decoded with the palette given by [`GVRTexture::palette()`].
+    pub fn decode(&self) -> Result<RGBAImage, GVRDecodeError> {
+        self.decode_mip_level(0)
+    }
+
+    /// Decodes the given mip `level` of this [`GVRTexture`] into an [`RGBAImage`], where level 0
+    /// is the base texture.
+    ///
+    /// Palettized textures are decoded with the palette given by [`GVRTexture::palette()`].
+    pub fn decode_mip_level(&self, level: usize) -> Result<RGBAImage, GVRDecodeError> {
+        let levels = self.header.mip_levels();
+        let mip_level = levels
+            .get(level)
+            .ok_or(GVRDecodeError::MipLevelOutOfRange {
+                level,
+                count: levels.len(),
+            })?;
+
+        let data = self
+            .data
+            .get_ref()
+            .get(mip_level.offset..)
+            .unwrap_or_default();
+
+        decode::decode_texture_data(
+            self.header.data_format,
+            data,
+            mip_level.width,
+            mip_level.height,
+            self.palette().as_ref(),
+        )
+    }
+
+    /// Reads the raw value of the texel at `x` and `y` in the given mip `level`, if it's present.
+    pub fn texel(&self, level: usize, x: u32, y: u32) -> Option<decode::GVRTexel> {
+        let mip_level = self.mip_levels().into_iter().nth(level)?;
+        if y >= mip_level.height {
+            return None;
+        }
+
+        let data = self.mip_level_data(level)?;
+        decode::read_texel(self.header.data_format, data, mip_level.width, x, y)
+    }
+
+    /// Overwrites the raw texture data of the given mip `level` with `data`, which has to be in
+    /// the format of this texture and exactly as big as the mip level.
+    pub fn replace_mip_level_data(
+        &mut self,
+        level: usize,
+        data: &[u8],
+    ) -> Result<(), GVRDecodeError> {
+        let levels = self.mip_levels();
+        let mip_level = levels
+            .get(level)
+            .ok_or(GVRDecodeError::MipLevelOutOfRange {
+                level,
+                count: levels.len(),
+            })?;
+
+        if data.len() < mip_level.size {
+            return Err(GVRDecodeError::NotEnoughData {
+                expected: mip_level.size,
+                found: data.len(),
+            });
+        }
+        if data.len() > mip_level.size {
+            return Err(GVRDecodeError::SizeMismatch {
+                expected: mip_level.size,
+                found: data.len(),
+            });
+        }
+
+        self.data.get_mut()[mip_level.range()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Checks if the given data in `reader` is a valid GVR texture, in any [`GVRVariant`].
+    ///
+    /// This assumes that the `reader` is at the very start of the file!
+    /// If it's a valid GVR texture, the `reader` position is returned back to the start.
+    /// Otherwise the `reader` position will be altered when this function returns.
+    pub fn validate<R: Read + Seek>(reader: &mut R) -> Result<(), GVRError> {
+        GVRChunkLayout::read(reader).map(|_| ())
+    }
+
+    /// Calculates the size of the given GVR texture from the data in `reader`. This covers the
+    /// global index header (if there is one) and the "GVRT" chunk, but not anything that comes
+    /// after the chunk.
+    ///
+    /// This assumes that the data in `reader` is a valid GVR texture!
+    /// This means a call to [`GVRTexture::validate()`] should be performed beforehand.
+    ///
+    /// This also assumes that the `reader` is at the very start of the file!
+    /// If it's a valid GVR texture, the `reader` position is returned back to the start.
+    /// Otherwise the `reader` position will be altered when this function returns.
+    pub fn read_texture_size<R: Read + Seek>(reader: &mut R) -> Result<u32, GVRError> {
+        let layout = GVRChunkLayout::read(reader)?;
+        let tex_size = layout.gvrt_offset + layout.gvrt_size;
+
+        tex_size.try_into().map_err(|_| GVRError::BadValue {
+            field: "GVRT size",
+            offset: layout.gvrt_offset + GVRT_SIZE_OFFSET,
+            value: (layout.gvrt_size - CHUNK_HEADER_SIZE) as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mipmap::{GVRMipmapFilter, GVRMipmaps};
+
+    fn make_header(flags_format: u8, data_format: u8, width: u16, height: u16) -> Vec<u8> {
+        let mut buf = vec![];
+        buf.extend_from_slice(b"GCIX");
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&0x1234u32.to_be_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(b"GVRT");
+        buf.extend_from_slice(&8u32.to_le_bytes());
+        buf.extend_from_slice(&[0, 0, flags_format, data_format]);
+        buf.extend_from_slice(&width.to_be_bytes());
+        buf.extend_from_slice(&height.to_be_bytes());
+        buf
+    }
+
+    #[test]
+    fn parse_header() {
+        let mut cursor = Cursor::new(make_header(0x21, 0x9, 64, 32));
+        let header = GVRHeader::read(&mut cursor).unwrap();
+
+        assert_eq!(cursor.position(), 0);
+        assert_eq!(header.global_index, 0x1234);
+        assert_eq!(header.palette_format, GVRPaletteFormat::RGB5A3);
+        assert_eq!(header.data_format, GVRDataFormat::C8);
+        assert_eq!((header.width, header.height), (64, 32));
+        assert!(header.has_mipmaps());
+        assert!(!header.has_internal_palette());
+    }
+
+    #[test]
+    fn decode_with_palettes() {
+        // C4 texture with an embedded RGB565 palette, where index 1 is red
+        let mut buf = make_header(0x18, 0x8, 8, 8);
+        let mut palette = [0u8; 32];
+        palette[2..4].copy_from_slice(&0xF800u16.to_be_bytes());
+        buf.extend_from_slice(&palette);
+        buf.extend_from_slice(&[0x11; 32]);
+
+        let mut tex = GVRTexture::new(
+            String::new(),
+            buf.len() as u32,
+            GVRHeader::read(&mut Cursor::new(buf.clone())).unwrap(),
+            Cursor::new(buf.clone()),
+        );
+        assert_eq!(tex.decode().unwrap().get_pixel(7, 7), [0xFF, 0, 0, 0xFF]);
+
+        // Same texture data, relying on an external palette instead
+        buf[0x1A] = 0x02;
+        buf.drain(0x20..0x40);
+        tex.header = GVRHeader::read(&mut Cursor::new(buf.clone())).unwrap();
+        tex.data = Cursor::new(buf);
+        assert_eq!(tex.decode(), Err(GVRDecodeError::MissingPalette));
+
+        tex.external_palette = Some(GVRPalette::new(GVRPaletteFormat::IA8, vec![0x0000, 0xFF80]));
+        assert_eq!(
+            tex.decode().unwrap().get_pixel(0, 0),
+            [0x80, 0x80, 0x80, 0xFF]
+        );
+    }
+
+    #[test]
+    fn mip_levels() {
+        // 16x8 RGB565 texture with mipmaps: 16x8, 8x4, 4x2, 2x1, 1x1
+        let mut buf = make_header(0x01, 0x4, 16, 8);
+        buf.extend_from_slice(&[0; 0x100 + 0x40 + 0x20 * 3]);
+
+        let mut tex = GVRTexture::new(
+            String::new(),
+            buf.len() as u32,
+            GVRHeader::read(&mut Cursor::new(buf.clone())).unwrap(),
+            Cursor::new(buf),
+        );
+
+        let levels = tex.mip_levels();
+        assert_eq!(levels.len(), 5);
+        assert_eq!(levels[1].range(), 0x120..0x160);
+        assert_eq!((levels[2].width, levels[2].height), (4, 2));
+        assert_eq!((levels[4].width, levels[4].height), (1, 1));
+        assert_eq!(levels[4].range(), 0x1A0..0x1C0);
+
+        tex.replace_mip_level_data(4, &[0xFF; 0x20]).unwrap();
+        assert_eq!(tex.decode_mip_level(4).unwrap().pixels, vec![0xFF; 4]);
+        assert_eq!(tex.decode().unwrap().get_pixel(0, 0), [0, 0, 0, 0xFF]);
+
+        assert_eq!(
+            tex.replace_mip_level_data(1, &[0; 0x20]),
+            Err(GVRDecodeError::NotEnoughData {
+                expected: 0x40,
+                found: 0x20
+            })
+        );
+        assert_eq!(
+            tex.replace_mip_level_data(1, &[0; 0x60]),
+            Err(GVRDecodeError::SizeMismatch {
+                expected: 0x40,
+                found: 0x60
+            })
+        );
+        assert!(tex.decode_mip_level(5).is_err());
+
+        // Levels that don't fit in the data are left out
+        tex.data.get_mut().truncate(0x1A0);
+        assert_eq!(tex.mip_levels().len(), 4);
+    }
+
+    #[test]
+    fn encode_full_texture() {
+        let mut image = RGBAImage::new(6, 3);
+        image.set_pixel(5, 2, [0xFF, 0, 0, 0xFF]);
+
+        let options = GVREncodeOptions {
+            global_index: 0x1234,
+            ..GVREncodeOptions::new(GVRDataFormat::RGB565)
+        };
+        let tex = GVRTexture::encode("red".to_string(), &image, &options).unwrap();
+
+        // 6x3 takes up 2 4x4 blocks
+        assert_eq!(tex.size, 0x20 + 0x40);
+        let mut cursor = Cursor::new(tex.data.get_ref().clone());
+        assert_eq!(GVRTexture::validate(&mut cursor), Ok(()));
+        assert_eq!(GVRTexture::read_texture_size(&mut cursor), Ok(tex.size));
+        assert_eq!(GVRHeader::read(&mut cursor).unwrap(), tex.header);
+        assert_eq!(&tex.data.get_ref()[0x14..0x18], &0x48u32.to_le_bytes());
+        assert_eq!(
+            &tex.data.get_ref()[0x18..0x20],
+            &make_header(0, 0x4, 6, 3)[0x18..]
+        );
+
+        let decoded = tex.decode().unwrap();
+        assert_eq!(decoded.get_pixel(5, 2), [0xFF, 0, 0, 0xFF]);
+        assert_eq!(decoded.get_pixel(0, 0), [0, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn encode_palettized_texture() {
+        let mut image = RGBAImage::new(8, 8);
+        image.set_pixel(0, 0, [0xFF, 0, 0, 0xFF]);
+        image.set_pixel(7, 7, [0, 0, 0xFF, 0x80]);
+
+        for format in [GVRDataFormat::C4, GVRDataFormat::C8] {
+            for placement in [GVRPalettePlacement::Embedded, GVRPalettePlacement::External] {
+                let options = GVREncodeOptions {
+                    palette_placement: placement,
+                    ..GVREncodeOptions::new(format)
+                };
+                let tex = GVRTexture::encode(String::new(), &image, &options).unwrap();
+                let header = GVRHeader::read(&mut Cursor::new(tex.data.get_ref().clone())).unwrap();
+
+                assert_eq!(header.palette_format, GVRPaletteFormat::RGB5A3);
+                assert_eq!(
+                    header.has_internal_palette(),
+                    placement == GVRPalettePlacement::Embedded
+                );
+                assert_eq!(
+                    tex.external_palette.is_some(),
+                    !header.has_internal_palette()
+                );
+                assert_eq!(
+                    tex.size as usize,
+                    header.data_offset() + format.data_size(8, 8)
+                );
+
+                let decoded = tex.decode().unwrap();
+                assert_eq!(decoded.get_pixel(0, 0), [0xFF, 0, 0, 0xFF]);
+                assert_eq!(decoded.get_pixel(7, 7), [0, 0, 0xFF, 0x92]);
+                assert_eq!(decoded.get_pixel(3, 3), [0; 4]);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_mipmaps() {
+        let mut image = RGBAImage::new(16, 8);
+        image
+            .pixels
+            .chunks_exact_mut(4)
+            .for_each(|p| p.copy_from_slice(&[0, 0xFF, 0, 0xFF]));
+
+        for format in [
+            GVRDataFormat::CMPR,
+            GVRDataFormat::C4,
+            GVRDataFormat::RGB5A3,
+        ] {
+            let options = GVREncodeOptions {
+                mipmaps: GVRMipmaps::Full,
+                ..GVREncodeOptions::new(format)
+            };
+            let tex = GVRTexture::encode(String::new(), &image, &options).unwrap();
+
+            assert!(tex.header.has_mipmaps());
+            assert_eq!(tex.mip_levels().len(), 5);
+            assert_eq!(tex.size as usize, tex.mip_levels()[4].range().end);
+            assert_eq!(
+                tex.decode_mip_level(4).unwrap().pixels,
+                vec![0, 0xFF, 0, 0xFF]
+            );
+        }
+
+        // A partial chain is completed down to 1x1, so it matches the header
+        let partial = mipmap::generate_mip_levels(&image, 2, GVRMipmapFilter::Box, false);
+        let options = GVREncodeOptions::new(GVRDataFormat::I8);
+        let tex = GVRTexture::encode_mip_levels(String::new(), &partial, &options).unwrap();
+        assert_eq!(tex.header.mip_level_count(), 5);
+        assert_eq!(tex.mip_levels().len(), 5);
+        assert_eq!(tex.size as usize, tex.mip_levels()[4].range().end);
+        assert_eq!(tex.decode_mip_level(4).unwrap().get_pixel(0, 0), [0x96; 4]);
+
+        // Replacing the texture keeps the mipmaps
+        let options = GVREncodeOptions::matching(&tex);
+        assert_eq!(options.format, GVRDataFormat::I8);
+        assert_eq!(options.mipmaps, GVRMipmaps::Full);
+    }
+
+    #[test]
+    fn convert_format() {
+        let mut image = RGBAImage::new(16, 8);
+        image
+            .pixels
+            .chunks_exact_mut(4)
+            .for_each(|p| p.copy_from_slice(&[0xFF, 0, 0, 0x80]));
+
+        let options = GVREncodeOptions {
+            mipmaps: GVRMipmaps::Full,
+            global_index: 7,
+            ..GVREncodeOptions::new(GVRDataFormat::ARGB8)
+        };
+        let tex = GVRTexture::encode("tex".into(), &image, &options).unwrap();
+
+        let mut options = GVREncodeOptions::matching(&tex);
+        options.format = GVRDataFormat::RGB5A3;
+        let converted = tex.convert(&options).unwrap();
+
+        assert_eq!(converted.name, "tex");
+        assert_eq!(converted.header.data_format, GVRDataFormat::RGB5A3);
+        assert_eq!(converted.header.global_index, 7);
+        assert_eq!(converted.mip_levels().len(), 5);
+        assert_eq!(converted.size as usize, converted.data.get_ref().len());
+        // RGB5A3 takes up half the space of ARGB8
+        assert_eq!(
+            converted.size - GVR_HEADER_SIZE,
+            (tex.size - GVR_HEADER_SIZE) / 2
+        );
+        assert_eq!(
+            converted.decode_mip_level(2).unwrap().get_pixel(0, 0),
+            [0xFF, 0, 0, 0x92]
+        );
+    }
+
+    #[test]
+    fn parse_header_bad_magic() {
+        let mut buf = make_header(0, 0xE, 8, 8);
+        buf[0x10..0x14].copy_from_slice(b"PVRT");
+        let err = GVRHeader::read(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(
+            err,
+            GVRError::BadMagic {
+                field: "GVRT magic",
+                offset: 0x10,
+                found: *b"PVRT",
+            }
+        );
+    }
+
+    #[test]
+    fn parse_header_bad_format() {
+        let err = GVRHeader::read(&mut Cursor::new(make_header(0, 0x7, 8, 8))).unwrap_err();
+
+        assert_eq!(
+            err,
+            GVRError::UnsupportedFormat {
+                field: "data format",
+                offset: 0x1B,
+                code: 0x7,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_header_truncated() {
+        let mut buf = make_header(0, 0xE, 8, 8);
+        buf.truncate(0x1D);
+        let err = GVRHeader::read(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(
+            err,
+            GVRError::TruncatedHeader {
+                field: "width",
+                offset: 0x1C,
+            }
+        );
+    }
+
+    #[test]
+    fn read_variants() {
+        // 8x8 CMPR texture, with the GVRT size covering the texture data
+        let mut gcix = make_header(0, 0xE, 8, 8);
+        gcix[0x14..0x18].copy_from_slice(&0x28u32.to_le_bytes());
+        gcix.extend_from_slice(&[0xAB; 0x20]);
+
+        let mut gbix = gcix.clone();
+        gbix[..4].copy_from_slice(b"GBIX");
+        // Trailing padding and an extra chunk after the texture
+        gbix.extend_from_slice(&[0; 0x10]);
+        gbix.extend_from_slice(b"GVPL");
+
+        let headerless = gcix[0x10..].to_vec();
+
+        for (buf, variant, global_index) in [
+            (gcix.clone(), GVRVariant::GCIX, 0x1234),
+            (gbix, GVRVariant::GBIX, 0x1234),
+            (headerless, GVRVariant::Headerless, 0),
+        ] {
+            let tex = GVRTexture::new_from_reader(String::new(), &mut Cursor::new(buf)).unwrap();
+
+            let mut expected = gcix.clone();
+            expected[0x8..0xC].copy_from_slice(&u32::to_be_bytes(global_index));
+
+            assert_eq!(tex.source_variant, variant);
+            assert_eq!(tex.header.global_index, global_index);
+            assert_eq!(tex.size, 0x40);
+            assert_eq!(tex.data.get_ref(), &expected);
+        }
+    }
+
+    #[test]
+    fn read_from_borrowed_buffer() {
+        let mut tex_buf = make_header(0, 0xE, 8, 8);
+        tex_buf[0x14..0x18].copy_from_slice(&0x28u32.to_le_bytes());
+        tex_buf.extend_from_slice(&[0xAB; 0x20]);
+
+        // Texture in the middle of a bigger buffer, followed by some other data
+        let mut file = vec![0xFF; 0x30];
+        file.extend_from_slice(&tex_buf);
+        file.extend_from_slice(&[0xCD; 0x10]);
+
+        let mut reader = Cursor::new(&file[..]);
+        reader.set_position(0x30);
+        GVRTexture::validate(&mut reader).unwrap();
+        assert_eq!(GVRTexture::read_texture_size(&mut reader).unwrap(), 0x40);
+        assert_eq!(reader.position(), 0x30);
+
+        let tex = GVRTexture::new_from_reader(String::new(), &mut reader).unwrap();
+        assert_eq!(tex.data.get_ref(), &tex_buf);
+        assert_eq!(reader.position(), 0x70);
+
+        let tex = GVRTexture::new_from_bytes(String::new(), &file[0x30..]).unwrap();
+        assert_eq!(tex.data.get_ref(), &tex_buf);
+    }
+
+    #[test]
+    fn read_variant_bad_magic() {
+        let mut buf = make_header(0, 0xE, 8, 8);
+        buf[..4].copy_from_slice(b"PVRT");
+        let err = GVRHeader::read(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(
+            err,
+            GVRError::BadMagic {
+                field: "magic",
+                offset: 0,
+                found: *b"PVRT",
+            }
+        );
+
+        // The size field of the GVRT chunk points past the end of the file
+        let mut buf = make_header(0, 0xE, 8, 8);
+        buf[0x14..0x18].copy_from_slice(&0x28u32.to_le_bytes());
+        let result = GVRTexture::new_from_reader(String::new(), &mut Cursor::new(buf));
+        assert_eq!(
+            result.err().unwrap(),
+            GVRError::SizeExceedsBuffer {
+                field: "GVRT size",
+                offset: 0x14,
+                size: 0x28,
+                available: 0x08,
+            }
+        );
+    }
+}
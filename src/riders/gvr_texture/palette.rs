@@ -0,0 +1,239 @@
+//! This module contains the functionality to work with the palettes of palettized GVR textures,
+//! either embedded in the texture itself or stored in separate GVPL (`.gvp`) palette files.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::{
+    decode::{ia8_to_rgba, rgb565_to_rgba, rgb5a3_to_rgba},
+    write_gcix_header, GVRError, GVRPaletteFormat,
+};
+
+/// Offset of the palette format in a "GVPL" chunk, relative to the start of the chunk.
+const GVPL_FORMAT_OFFSET: u64 = 0x09;
+/// Offset of the palette entry count in a "GVPL" chunk, relative to the start of the chunk.
+const GVPL_ENTRY_COUNT_OFFSET: u64 = 0x0E;
+/// The size of the "GVPL" chunk header. Palette entries start right after it.
+const GVPL_HEADER_SIZE: u64 = 0x10;
+
+/// Represents the palette of a palettized GVR texture.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GVRPalette {
+    /// The format of the palette entries.
+    pub format: GVRPaletteFormat,
+    /// The raw palette entries, each in the format given by [`GVRPalette::format`].
+    pub entries: Vec<u16>,
+}
+
+impl GVRPalette {
+    /// Constructs a new [`GVRPalette`] from the given raw `entries`.
+    pub fn new(format: GVRPaletteFormat, entries: Vec<u16>) -> Self {
+        Self { format, entries }
+    }
+
+    /// Reads `entry_count` big-endian palette entries of the given `format` from the start of
+    /// `data`.
+    ///
+    /// Returns [`None`] if `data` is too short to contain all the entries.
+    pub fn from_entry_data(
+        format: GVRPaletteFormat,
+        data: &[u8],
+        entry_count: usize,
+    ) -> Option<Self> {
+        let entry_data = data.get(..entry_count * 2)?;
+        let entries = entry_data
+            .chunks_exact(2)
+            .map(|e| u16::from_be_bytes([e[0], e[1]]))
+            .collect();
+
+        Some(Self::new(format, entries))
+    }
+
+    /// Parses a GVPL palette file from the given `reader`. The "GVPL" chunk may optionally be
+    /// preceded by a "GCIX" header.
+    ///
+    /// This assumes that the `reader` is at the very start of the file!
+    pub fn read_gvpl<R: Read + Seek>(reader: &mut R) -> Result<Self, GVRError> {
+        let truncated = GVRError::truncated;
+        let start_pos = reader
+            .stream_position()
+            .map_err(|_| truncated("GVPL magic", 0))?;
+        let mut magic = [0; 4];
+
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| truncated("GVPL magic", 0))?;
+
+        // Skip the GCIX header if there is one
+        let mut chunk_offset = 0;
+        if &magic == b"GCIX" {
+            chunk_offset = 0x10;
+            reader
+                .seek(SeekFrom::Start(start_pos + chunk_offset))
+                .map_err(|_| truncated("GVPL magic", chunk_offset))?;
+            reader
+                .read_exact(&mut magic)
+                .map_err(|_| truncated("GVPL magic", chunk_offset))?;
+        }
+
+        if &magic != b"GVPL" {
+            return Err(GVRError::BadMagic {
+                field: "GVPL magic",
+                offset: chunk_offset,
+                found: magic,
+            });
+        }
+
+        let format_offset = chunk_offset + GVPL_FORMAT_OFFSET;
+        reader
+            .seek(SeekFrom::Start(start_pos + format_offset))
+            .map_err(|_| truncated("palette format", format_offset))?;
+        let format_code = reader
+            .read_u8()
+            .map_err(|_| truncated("palette format", format_offset))?;
+        let format =
+            GVRPaletteFormat::from_code(format_code).ok_or(GVRError::UnsupportedFormat {
+                field: "palette format",
+                offset: format_offset,
+                code: format_code,
+            })?;
+
+        let entry_count_offset = chunk_offset + GVPL_ENTRY_COUNT_OFFSET;
+        reader
+            .seek(SeekFrom::Start(start_pos + entry_count_offset))
+            .map_err(|_| truncated("palette entry count", entry_count_offset))?;
+        let entry_count = reader
+            .read_u16::<BigEndian>()
+            .map_err(|_| truncated("palette entry count", entry_count_offset))?;
+
+        // The entries start right after the entry count. A read error is handled the same way as
+        // the file ending early.
+        let mut data = vec![];
+        let _ = reader
+            .take(u64::from(entry_count) * 2)
+            .read_to_end(&mut data);
+
+        Self::from_entry_data(format, &data, entry_count.into()).ok_or(
+            GVRError::SizeExceedsBuffer {
+                field: "palette entry count",
+                offset: entry_count_offset,
+                size: u64::from(entry_count) * 2,
+                available: data.len() as u64,
+            },
+        )
+    }
+
+    /// Serializes this palette into a full GVPL palette file, including a "GCIX" header with the
+    /// given `global_index`.
+    pub fn to_gvpl_bytes(&self, global_index: u32) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(0x10 + GVPL_HEADER_SIZE as usize + self.entries.len() * 2);
+
+        write_gcix_header(&mut buf, global_index);
+
+        // The size counts everything after the size field itself
+        buf.extend_from_slice(b"GVPL");
+        let gvpl_size = GVPL_HEADER_SIZE as usize - 0x8 + self.entries.len() * 2;
+        buf.write_u32::<LittleEndian>(gvpl_size as u32).unwrap();
+        buf.write_u8(0).unwrap();
+        buf.write_u8(self.format.code()).unwrap();
+        buf.write_u32::<BigEndian>(0).unwrap();
+        buf.write_u16::<BigEndian>(self.entries.len() as u16)
+            .unwrap();
+        buf.extend_from_slice(&self.entry_bytes());
+
+        buf
+    }
+
+    /// Returns the raw palette entries as big-endian bytes, the way they're stored in both GVR
+    /// textures and GVPL files.
+    pub fn entry_bytes(&self) -> Vec<u8> {
+        self.entries.iter().flat_map(|e| e.to_be_bytes()).collect()
+    }
+
+    /// Converts all the palette entries into RGBA8 colors.
+    pub fn colors(&self) -> Vec<[u8; 4]> {
+        let convert = match self.format {
+            GVRPaletteFormat::IA8 => ia8_to_rgba,
+            GVRPaletteFormat::RGB565 => rgb565_to_rgba,
+            GVRPaletteFormat::RGB5A3 => rgb5a3_to_rgba,
+        };
+
+        self.entries.iter().map(|&e| convert(e)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn make_gvpl(with_gcix: bool, format: u8, entries: &[u16]) -> Vec<u8> {
+        let mut buf = vec![];
+        if with_gcix {
+            buf.extend_from_slice(b"GCIX");
+            buf.extend_from_slice(&8u32.to_le_bytes());
+            buf.extend_from_slice(&[0; 8]);
+        }
+        buf.extend_from_slice(b"GVPL");
+        buf.extend_from_slice(&(8 + entries.len() as u32 * 2).to_le_bytes());
+        buf.extend_from_slice(&[0, format, 0, 0, 0, 0]);
+        buf.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for entry in entries {
+            buf.extend_from_slice(&entry.to_be_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn read_gvpl() {
+        for with_gcix in [false, true] {
+            let buf = make_gvpl(with_gcix, 0x1, &[0xF800, 0x001F]);
+            let palette = GVRPalette::read_gvpl(&mut Cursor::new(buf)).unwrap();
+
+            assert_eq!(palette.format, GVRPaletteFormat::RGB565);
+            assert_eq!(
+                palette.colors(),
+                vec![[0xFF, 0, 0, 0xFF], [0, 0, 0xFF, 0xFF]]
+            );
+        }
+    }
+
+    #[test]
+    fn write_gvpl() {
+        let palette = GVRPalette::new(GVRPaletteFormat::RGB5A3, vec![0x8000, 0x7FFF]);
+        let buf = palette.to_gvpl_bytes(0);
+
+        assert_eq!(&buf[0x10..], &make_gvpl(false, 0x2, &[0x8000, 0x7FFF])[..]);
+        assert_eq!(
+            GVRPalette::read_gvpl(&mut Cursor::new(buf)).unwrap(),
+            palette
+        );
+    }
+
+    #[test]
+    fn read_gvpl_truncated() {
+        let mut buf = make_gvpl(false, 0x2, &[0x8000, 0x0000]);
+        buf.pop();
+        let err = GVRPalette::read_gvpl(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(
+            err,
+            GVRError::SizeExceedsBuffer {
+                field: "palette entry count",
+                offset: 0x0E,
+                size: 4,
+                available: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn read_gvpl_bad_format() {
+        let buf = make_gvpl(true, 0x3, &[]);
+        let err = GVRPalette::read_gvpl(&mut Cursor::new(buf)).unwrap_err();
+
+        assert_eq!(err.field(), "palette format");
+        assert_eq!(err.offset(), 0x19);
+    }
+}
@@ -0,0 +1,250 @@
+//! This module contains the color quantization used to build palettes for palettized GVR
+//! textures out of plain RGBA8 images.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    encode::{rgba_to_ia8, rgba_to_rgb565, rgba_to_rgb5a3},
+    image::RGBAImage,
+    palette::GVRPalette,
+    GVRPaletteFormat,
+};
+
+/// The amount of k-means passes done to refine the palette after the median cut.
+const REFINE_PASSES: usize = 4;
+
+/// A unique color of the image along with the amount of pixels using it.
+type WeightedColor = ([u8; 4], u32);
+
+/// Builds a palette of at most `max_colors` entries of the given `format` for `image`.
+pub(crate) fn build_palette(
+    image: &RGBAImage,
+    max_colors: usize,
+    format: GVRPaletteFormat,
+) -> GVRPalette {
+    let mut histogram: HashMap<[u8; 4], u32> = HashMap::new();
+    for pixel in image.pixels.chunks_exact(4) {
+        *histogram.entry(pixel.try_into().unwrap()).or_default() += 1;
+    }
+
+    let mut colors: Vec<WeightedColor> = histogram.into_iter().collect();
+    // Keeps the result deterministic, as the iteration order of a HashMap isn't
+    colors.sort_unstable();
+
+    let mut centers = median_cut(&colors, max_colors);
+    refine(&colors, &mut centers);
+
+    let convert = match format {
+        GVRPaletteFormat::IA8 => rgba_to_ia8,
+        GVRPaletteFormat::RGB565 => rgba_to_rgb565,
+        GVRPaletteFormat::RGB5A3 => rgba_to_rgb5a3,
+    };
+
+    // Different colors may end up the same after conversion, so only keep the first one
+    let mut seen = HashSet::new();
+    let entries: Vec<u16> = centers
+        .iter()
+        .map(|&c| convert(c))
+        .filter(|&e| seen.insert(e))
+        .collect();
+
+    GVRPalette::new(format, entries)
+}
+
+/// Maps every pixel of `image` to the closest color in `palette`, returning the palette index of
+/// every pixel in row-major order.
+///
+/// If `dither` is set, Floyd-Steinberg dithering is applied while mapping the pixels.
+pub(crate) fn map_to_palette(image: &RGBAImage, palette: &GVRPalette, dither: bool) -> Vec<u8> {
+    // Map against the colors the hardware will actually show
+    let palette_colors = palette.colors();
+
+    if dither {
+        map_dithered(image, &palette_colors)
+    } else {
+        image
+            .pixels
+            .chunks_exact(4)
+            .map(|p| nearest(&palette_colors, p.try_into().unwrap()) as u8)
+            .collect()
+    }
+}
+
+/// Splits the colors into at most `max_colors` boxes, repeatedly cutting the box with the
+/// widest channel range at its weighted median, and returns the average color of each box.
+fn median_cut(colors: &[WeightedColor], max_colors: usize) -> Vec<[u8; 4]> {
+    if colors.len() <= max_colors {
+        return colors.iter().map(|&(color, _)| color).collect();
+    }
+
+    let mut boxes: Vec<Vec<WeightedColor>> = vec![colors.to_vec()];
+
+    while boxes.len() < max_colors {
+        // Find the box and channel with the widest range
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = (0..4)
+                    .map(|ch| {
+                        let min = b.iter().map(|c| c.0[ch]).min().unwrap();
+                        let max = b.iter().map(|c| c.0[ch]).max().unwrap();
+                        (ch, max - min)
+                    })
+                    .max_by_key(|&(_, range)| range)
+                    .unwrap();
+                (i, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range);
+
+        let Some((box_idx, channel, _)) = widest else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(box_idx);
+        colors.sort_unstable_by_key(|c| c.0[channel]);
+
+        let total: u32 = colors.iter().map(|c| c.1).sum();
+        let mut acc = 0;
+        let mut split = colors.len() - 1;
+        for (i, color) in colors.iter().enumerate() {
+            acc += color.1;
+            if acc * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, colors.len() - 1);
+
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| weighted_average(b)).collect()
+}
+
+/// Refines the palette with a few k-means passes, moving every palette color to the average of
+/// the colors closest to it.
+fn refine(colors: &[WeightedColor], centers: &mut [[u8; 4]]) {
+    for _ in 0..REFINE_PASSES {
+        let mut clusters: Vec<Vec<WeightedColor>> = vec![vec![]; centers.len()];
+        for &color in colors {
+            clusters[nearest(centers, color.0)].push(color);
+        }
+
+        for (center, cluster) in centers.iter_mut().zip(&clusters) {
+            if !cluster.is_empty() {
+                *center = weighted_average(cluster);
+            }
+        }
+    }
+}
+
+fn weighted_average(colors: &[WeightedColor]) -> [u8; 4] {
+    let total: u64 = colors.iter().map(|c| c.1 as u64).sum();
+    let mut sum = [0u64; 4];
+    for (color, weight) in colors {
+        for ch in 0..4 {
+            sum[ch] += color[ch] as u64 * *weight as u64;
+        }
+    }
+
+    sum.map(|s| ((s + total / 2) / total) as u8)
+}
+
+/// Finds the index of the palette color closest to `color`.
+fn nearest(palette: &[[u8; 4]], color: [u8; 4]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| {
+            (0..4)
+                .map(|ch| (p[ch] as i32 - color[ch] as i32).pow(2))
+                .sum::<i32>()
+        })
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+/// Maps every pixel of `image` to the closest palette color, diffusing the error to the
+/// neighbouring pixels with Floyd-Steinberg dithering.
+fn map_dithered(image: &RGBAImage, palette: &[[u8; 4]]) -> Vec<u8> {
+    let width = image.width as usize;
+    let mut pixels: Vec<[f32; 4]> = image
+        .pixels
+        .chunks_exact(4)
+        .map(|p| [p[0], p[1], p[2], p[3]].map(f32::from))
+        .collect();
+    let mut indices = Vec::with_capacity(pixels.len());
+
+    for i in 0..pixels.len() {
+        let x = i % width;
+        let color = pixels[i].map(|v| v.round().clamp(0., 255.) as u8);
+        let index = nearest(palette, color);
+        indices.push(index as u8);
+
+        let chosen = palette[index];
+        let error: Vec<f32> = (0..4).map(|ch| pixels[i][ch] - chosen[ch] as f32).collect();
+
+        let mut diffuse = |idx: usize, weight: f32| {
+            if let Some(pixel) = pixels.get_mut(idx) {
+                for ch in 0..4 {
+                    pixel[ch] += error[ch] * weight;
+                }
+            }
+        };
+
+        if x + 1 < width {
+            diffuse(i + 1, 7. / 16.);
+            diffuse(i + width + 1, 1. / 16.);
+        }
+        if x > 0 {
+            diffuse(i + width - 1, 3. / 16.);
+        }
+        diffuse(i + width, 5. / 16.);
+    }
+
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn few_colors_are_kept_exactly() {
+        let mut image = RGBAImage::new(4, 4);
+        image.set_pixel(0, 0, [0xFF, 0, 0, 0xFF]);
+        image.set_pixel(1, 0, [0, 0xFF, 0, 0x00]);
+
+        let palette = build_palette(&image, 16, GVRPaletteFormat::RGB5A3);
+        let indices = map_to_palette(&image, &palette, false);
+        let colors = palette.colors();
+
+        assert_eq!(palette.entries.len(), 3);
+        assert_eq!(colors[indices[0] as usize], [0xFF, 0, 0, 0xFF]);
+        assert_eq!(colors[indices[1] as usize][3], 0);
+        assert_eq!(colors[indices[2] as usize], [0; 4]);
+    }
+
+    #[test]
+    fn palette_size_is_limited() {
+        let mut image = RGBAImage::new(32, 32);
+        for y in 0..32 {
+            for x in 0..32 {
+                image.set_pixel(x, y, [(x * 8) as u8, (y * 8) as u8, 0x80, 0xFF]);
+            }
+        }
+
+        for dither in [false, true] {
+            let palette = build_palette(&image, 16, GVRPaletteFormat::RGB565);
+            let indices = map_to_palette(&image, &palette, dither);
+            assert!(palette.entries.len() <= 16);
+            assert!(indices
+                .iter()
+                .all(|&i| (i as usize) < palette.entries.len()));
+        }
+    }
+}
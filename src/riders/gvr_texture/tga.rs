@@ -0,0 +1,403 @@
+//! This module contains the functionality to convert GVR textures to and from TGA images.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{
+    encode::GVREncodeOptions, image::RGBAImage, interchange::GVRInterchangeError, GVRTexture,
+};
+
+/// The size of the TGA header in bytes.
+const TGA_HEADER_SIZE: usize = 18;
+
+// Image types of TGA files. The RLE compressed variants are the same types with this bit set
+const TGA_TYPE_COLOR_MAPPED: u8 = 1;
+const TGA_TYPE_TRUE_COLOR: u8 = 2;
+const TGA_TYPE_GRAYSCALE: u8 = 3;
+const TGA_TYPE_RLE_BIT: u8 = 0x8;
+
+// Bits of the image descriptor, the last byte of the header
+const TGA_DESCRIPTOR_ALPHA_BITS: u8 = 0x0F;
+const TGA_DESCRIPTOR_RIGHT_TO_LEFT: u8 = 0x10;
+const TGA_DESCRIPTOR_TOP_TO_BOTTOM: u8 = 0x20;
+
+/// Reads a TGA file from `data` into an [`RGBAImage`].
+///
+/// True color (15, 16, 24 and 32 bits per pixel), grayscale (8 bits, optionally with 8 bits of
+/// alpha) and color-mapped images are supported, both uncompressed and RLE compressed.
+pub fn read_tga(data: &[u8]) -> Result<RGBAImage, GVRInterchangeError> {
+    let invalid = |reason: &str| GVRInterchangeError::InvalidTga(reason.to_string());
+
+    let header = data
+        .get(..TGA_HEADER_SIZE)
+        .ok_or(invalid("file is too short to contain a header"))?;
+
+    let id_length = header[0] as usize;
+    let color_map_type = header[1];
+    let image_type = header[2];
+    let color_map_first = LittleEndian::read_u16(&header[3..]) as usize;
+    let color_map_length = LittleEndian::read_u16(&header[5..]) as usize;
+    let color_map_depth = header[7];
+    let width = LittleEndian::read_u16(&header[12..]) as u32;
+    let height = LittleEndian::read_u16(&header[14..]) as u32;
+    let depth = header[16];
+    let descriptor = header[17];
+
+    if width == 0 || height == 0 {
+        return Err(invalid("image has no pixels"));
+    }
+
+    // Both depths are checked before any data is split up into pixels or color map entries
+    let compressed = image_type & TGA_TYPE_RLE_BIT != 0;
+    let bytes_per_pixel = (depth as usize).div_ceil(8);
+
+    let image_kind = image_type & !TGA_TYPE_RLE_BIT;
+    match image_kind {
+        TGA_TYPE_COLOR_MAPPED => {
+            if color_map_type != 1 || color_map_length == 0 || !matches!(depth, 8 | 16) {
+                return Err(invalid("color-mapped image has no valid color map"));
+            }
+        }
+        TGA_TYPE_TRUE_COLOR => {
+            if !matches!(depth, 15 | 16 | 24 | 32) {
+                return Err(GVRInterchangeError::InvalidTga(format!(
+                    "{depth} bits per pixel is not supported for true color images"
+                )));
+            }
+        }
+        TGA_TYPE_GRAYSCALE => {
+            if !matches!(depth, 8 | 16) {
+                return Err(GVRInterchangeError::InvalidTga(format!(
+                    "{depth} bits per pixel is not supported for grayscale images"
+                )));
+            }
+        }
+        _ => {
+            return Err(GVRInterchangeError::InvalidTga(format!(
+                "image type {image_type} is not supported"
+            )))
+        }
+    }
+
+    if color_map_type == 1 && !matches!(color_map_depth, 15 | 16 | 24 | 32) {
+        return Err(GVRInterchangeError::InvalidTga(format!(
+            "{color_map_depth} bits per color map entry is not supported"
+        )));
+    }
+
+    let has_alpha = descriptor & TGA_DESCRIPTOR_ALPHA_BITS != 0;
+    let mut offset = TGA_HEADER_SIZE + id_length;
+
+    // The color map is stored even for images that don't use it
+    let mut color_map = vec![];
+    if color_map_type == 1 {
+        let entry_size = (color_map_depth as usize).div_ceil(8);
+        let size = color_map_length * entry_size;
+        let map_data = data
+            .get(offset..offset + size)
+            .ok_or(invalid("file is too short to contain the color map"))?;
+        offset += size;
+
+        color_map = map_data
+            .chunks_exact(entry_size)
+            .map(|entry| read_true_color(entry, color_map_depth, has_alpha))
+            .collect::<Result<_, _>>()?;
+    }
+
+    let convert = |pixel: &[u8]| match image_kind {
+        TGA_TYPE_COLOR_MAPPED => {
+            let index = LittleEndian::read_uint(pixel, pixel.len()) as usize;
+            index
+                .checked_sub(color_map_first)
+                .and_then(|i| color_map.get(i))
+                .copied()
+                .ok_or_else(|| invalid("color map index is out of range"))
+        }
+        TGA_TYPE_GRAYSCALE => {
+            let alpha = pixel.get(1).copied().unwrap_or(0xFF);
+            Ok([pixel[0], pixel[0], pixel[0], alpha])
+        }
+        _ => read_true_color(pixel, depth, has_alpha),
+    };
+
+    let size = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|count| count.checked_mul(bytes_per_pixel))
+        .ok_or(invalid("image is too large"))?;
+    let pixel_data = data.get(offset..).unwrap_or_default();
+    let pixel_data = if compressed {
+        decompress_rle(pixel_data, size, bytes_per_pixel)
+            .ok_or(invalid("RLE compressed pixel data is truncated"))?
+    } else {
+        pixel_data
+            .get(..size)
+            .ok_or(invalid("file is too short to contain the pixel data"))?
+            .to_vec()
+    };
+
+    let mut image = RGBAImage::new(width, height);
+    for (i, pixel) in pixel_data.chunks_exact(bytes_per_pixel).enumerate() {
+        let mut x = i as u32 % width;
+        let mut y = i as u32 / width;
+
+        // Rows are stored bottom to top by default
+        if descriptor & TGA_DESCRIPTOR_TOP_TO_BOTTOM == 0 {
+            y = height - 1 - y;
+        }
+        if descriptor & TGA_DESCRIPTOR_RIGHT_TO_LEFT != 0 {
+            x = width - 1 - x;
+        }
+
+        image.set_pixel(x, y, convert(pixel)?);
+    }
+
+    Ok(image)
+}
+
+/// Writes the given `image` into a new uncompressed 32-bit TGA file, stored top to bottom.
+pub fn write_tga(image: &RGBAImage) -> Vec<u8> {
+    let mut buf = vec![0; TGA_HEADER_SIZE];
+    buf[2] = TGA_TYPE_TRUE_COLOR;
+    LittleEndian::write_u16(&mut buf[12..], image.width as u16);
+    LittleEndian::write_u16(&mut buf[14..], image.height as u16);
+    buf[16] = 32;
+    buf[17] = TGA_DESCRIPTOR_TOP_TO_BOTTOM | 8;
+
+    buf.extend(
+        image
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]]),
+    );
+
+    buf
+}
+
+/// Converts a single little-endian true color pixel (or color map entry) of the given `depth`
+/// into RGBA8. The alpha of 16-bit pixels is only used if the image is marked to have alpha.
+fn read_true_color(
+    pixel: &[u8],
+    depth: u8,
+    has_alpha: bool,
+) -> Result<[u8; 4], GVRInterchangeError> {
+    match depth {
+        15 | 16 => {
+            let value = LittleEndian::read_u16(pixel);
+            let expand = |v: u16| ((v << 3) | (v >> 2)) as u8;
+            let alpha = if depth == 16 && has_alpha && value & 0x8000 == 0 {
+                0
+            } else {
+                0xFF
+            };
+
+            Ok([
+                expand((value >> 10) & 0x1F),
+                expand((value >> 5) & 0x1F),
+                expand(value & 0x1F),
+                alpha,
+            ])
+        }
+        24 => Ok([pixel[2], pixel[1], pixel[0], 0xFF]),
+        32 => Ok([pixel[2], pixel[1], pixel[0], pixel[3]]),
+        _ => Err(GVRInterchangeError::InvalidTga(format!(
+            "{depth}-bit colors are not supported"
+        ))),
+    }
+}
+
+/// Decompresses RLE compressed pixel data into `size` bytes of raw pixels of `bytes_per_pixel`
+/// bytes each. Returns [`None`] if `data` ends too early.
+fn decompress_rle(data: &[u8], size: usize, bytes_per_pixel: usize) -> Option<Vec<u8>> {
+    // `size` comes from the header, so only reserve what `data` can actually expand to. Every
+    // packet takes at least one byte plus a pixel, and expands to at most 128 pixels.
+    let max_size = data.len() / (1 + bytes_per_pixel) * 128 * bytes_per_pixel;
+    let mut out = Vec::with_capacity(size.min(max_size));
+    let mut pos = 0;
+
+    while out.len() < size {
+        let packet = *data.get(pos)?;
+        let count = (packet & 0x7F) as usize + 1;
+        pos += 1;
+
+        if packet & 0x80 != 0 {
+            // Run-length packet, a single pixel repeated
+            let pixel = data.get(pos..pos + bytes_per_pixel)?;
+            for _ in 0..count {
+                out.extend_from_slice(pixel);
+            }
+            pos += bytes_per_pixel;
+        } else {
+            // Raw packet
+            let pixels = data.get(pos..pos + count * bytes_per_pixel)?;
+            out.extend_from_slice(pixels);
+            pos += count * bytes_per_pixel;
+        }
+    }
+
+    // Packets may cross the end of the image
+    out.truncate(size);
+    Some(out)
+}
+
+impl GVRTexture {
+    /// Encodes the TGA file in `data` into a new GVR texture named `name`, as per the given
+    /// `options`. See [`GVRTexture::encode()`] for more details.
+    pub fn from_tga(
+        name: String,
+        data: &[u8],
+        options: &GVREncodeOptions,
+    ) -> Result<Self, GVRInterchangeError> {
+        let image = read_tga(data)?;
+        Ok(GVRTexture::encode(name, &image, options)?)
+    }
+
+    /// Decodes the base texture of this [`GVRTexture`] and writes it into a new TGA file.
+    pub fn to_tga(&self) -> Result<Vec<u8>, GVRInterchangeError> {
+        Ok(write_tga(&self.decode()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::GVRDataFormat;
+
+    fn make_header(image_type: u8, width: u16, height: u16, depth: u8, descriptor: u8) -> Vec<u8> {
+        let mut buf = vec![0; TGA_HEADER_SIZE];
+        buf[2] = image_type;
+        buf[12..14].copy_from_slice(&width.to_le_bytes());
+        buf[14..16].copy_from_slice(&height.to_le_bytes());
+        buf[16] = depth;
+        buf[17] = descriptor;
+        buf
+    }
+
+    #[test]
+    fn tga_round_trip() {
+        let mut image = RGBAImage::new(3, 2);
+        image.set_pixel(0, 0, [0xFF, 0, 0, 0xFF]);
+        image.set_pixel(2, 1, [0x10, 0x20, 0x30, 0x40]);
+
+        assert_eq!(read_tga(&write_tga(&image)).unwrap(), image);
+    }
+
+    #[test]
+    fn read_rle_bottom_to_top() {
+        let mut buf = make_header(TGA_TYPE_TRUE_COLOR | TGA_TYPE_RLE_BIT, 2, 2, 24, 0);
+        // A run of 3 blue pixels, followed by a single raw red pixel
+        buf.extend_from_slice(&[0x82, 0xFF, 0, 0]);
+        buf.extend_from_slice(&[0x00, 0, 0, 0xFF]);
+
+        let image = read_tga(&buf).unwrap();
+        assert_eq!(image.get_pixel(0, 1), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(1, 1), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(0, 0), [0, 0, 0xFF, 0xFF]);
+        assert_eq!(image.get_pixel(1, 0), [0xFF, 0, 0, 0xFF]);
+    }
+
+    #[test]
+    fn read_color_mapped() {
+        let mut buf = make_header(TGA_TYPE_COLOR_MAPPED, 2, 1, 8, TGA_DESCRIPTOR_TOP_TO_BOTTOM);
+        buf[1] = 1;
+        buf[5..7].copy_from_slice(&2u16.to_le_bytes());
+        buf[7] = 24;
+        buf.extend_from_slice(&[0, 0xFF, 0, 0x80, 0x80, 0x80]);
+        buf.extend_from_slice(&[1, 0]);
+
+        let image = read_tga(&buf).unwrap();
+        assert_eq!(image.pixels, vec![0x80, 0x80, 0x80, 0xFF, 0, 0xFF, 0, 0xFF]);
+    }
+
+    #[test]
+    fn read_16_bit_alpha() {
+        let mut buf = make_header(
+            TGA_TYPE_TRUE_COLOR,
+            2,
+            1,
+            16,
+            TGA_DESCRIPTOR_TOP_TO_BOTTOM | 1,
+        );
+        buf.extend_from_slice(&0xFC00u16.to_le_bytes());
+        buf.extend_from_slice(&0x001Fu16.to_le_bytes());
+
+        let image = read_tga(&buf).unwrap();
+        assert_eq!(image.pixels, vec![0xFF, 0, 0, 0xFF, 0, 0, 0xFF, 0]);
+    }
+
+    #[test]
+    fn invalid_tga() {
+        let mut buf = make_header(TGA_TYPE_TRUE_COLOR | TGA_TYPE_RLE_BIT, 2, 2, 24, 0);
+        buf.extend_from_slice(&[0x81, 0xFF, 0, 0]);
+        assert!(matches!(
+            read_tga(&buf),
+            Err(GVRInterchangeError::InvalidTga(_))
+        ));
+
+        let buf = make_header(TGA_TYPE_TRUE_COLOR, 2, 2, 12, 0);
+        assert!(matches!(
+            read_tga(&buf),
+            Err(GVRInterchangeError::InvalidTga(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_tga_headers() {
+        let is_invalid =
+            |buf: &[u8]| matches!(read_tga(buf), Err(GVRInterchangeError::InvalidTga(_)));
+
+        // Color map entries of 0 bits
+        let mut buf = make_header(TGA_TYPE_COLOR_MAPPED, 2, 1, 8, 0);
+        buf[1] = 1;
+        buf[5..7].copy_from_slice(&2u16.to_le_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        assert!(is_invalid(&buf));
+
+        // A color map that isn't used by a true color image is still checked
+        let mut buf = make_header(TGA_TYPE_TRUE_COLOR, 1, 1, 24, 0);
+        buf[1] = 1;
+        buf[5..7].copy_from_slice(&1u16.to_le_bytes());
+        buf.extend_from_slice(&[0; 8]);
+        assert!(is_invalid(&buf));
+
+        // Pixels of 0 bits, in every image type
+        for image_type in [
+            0,
+            TGA_TYPE_COLOR_MAPPED,
+            TGA_TYPE_TRUE_COLOR,
+            TGA_TYPE_GRAYSCALE,
+            TGA_TYPE_TRUE_COLOR | TGA_TYPE_RLE_BIT,
+        ] {
+            let mut buf = make_header(image_type, 2, 2, 0, 0);
+            buf.extend_from_slice(&[0; 16]);
+            assert!(is_invalid(&buf), "image type {image_type}");
+        }
+
+        // Color-mapped pixels have to be 8 or 16-bit indices
+        let mut buf = make_header(TGA_TYPE_COLOR_MAPPED, 1, 1, 24, 0);
+        buf[1] = 1;
+        buf[5..7].copy_from_slice(&1u16.to_le_bytes());
+        buf[7] = 24;
+        buf.extend_from_slice(&[0; 8]);
+        assert!(is_invalid(&buf));
+
+        // The largest possible image, with only a single RLE packet
+        let mut buf = make_header(
+            TGA_TYPE_TRUE_COLOR | TGA_TYPE_RLE_BIT,
+            0xFFFF,
+            0xFFFF,
+            32,
+            0,
+        );
+        buf.extend_from_slice(&[0xFF, 0, 0, 0, 0]);
+        assert!(is_invalid(&buf));
+    }
+
+    #[test]
+    fn texture_tga_round_trip() {
+        let mut image = RGBAImage::new(4, 4);
+        image.set_pixel(1, 2, [0x12, 0x34, 0x56, 0x78]);
+        let options = GVREncodeOptions::new(GVRDataFormat::ARGB8);
+
+        let tex = GVRTexture::from_tga("test".into(), &write_tga(&image), &options).unwrap();
+        assert_eq!(read_tga(&tex.to_tga().unwrap()).unwrap(), image);
+    }
+}
@@ -4,12 +4,18 @@
 use std::{
     fs::File,
     io::{Cursor, Read, Seek, Write},
+    path::Path,
 };
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 use crate::util::Alignment;
 
+use super::{
+    gvr_texture::dolphin::{self, DolphinPackReport, DolphinTexturePack},
+    texture_archive::TextureArchive,
+};
+
 /// Represents a singular file in a folder in a PackMan archive.
 #[derive(Default)]
 pub struct PackManFile {
@@ -88,31 +94,59 @@ impl PackManArchive {
         Default::default()
     }
 
+    /// Creates a new [`PackManArchive`] by reading the archive from `reader`, starting at its
+    /// current position. See [`PackManArchive::read_from()`] for more details.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> std::io::Result<Self> {
+        let mut archive = Self::new_empty();
+        archive.read_from(reader)?;
+        Ok(archive)
+    }
+
+    /// Creates a new [`PackManArchive`] by reading the archive at the start of `data`. See
+    /// [`PackManArchive::read_from()`] for more details.
+    pub fn from_bytes(data: &[u8]) -> std::io::Result<Self> {
+        Self::from_reader(&mut Cursor::new(data))
+    }
+
     /// Reads the PackMan archive contents of the buffer stored in [`PackManArchive::cursor`].
     ///
     /// This assumes you created the archive via [`PackManArchive::new()`].
     pub fn read(&mut self) -> std::io::Result<()> {
+        let mut cursor = std::mem::take(&mut self.cursor);
+        let result = self.read_from(&mut cursor);
+        self.cursor = cursor;
+
+        result
+    }
+
+    /// Reads the PackMan archive contents from `reader`, starting at its current position. All
+    /// offsets in the archive are relative to that position, so the archive can be read straight
+    /// out of a bigger file. The last file in the archive extends to the end of `reader`.
+    pub fn read_from<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
         // TODO: add validation
-        let folder_count = self.cursor.read_u32::<BigEndian>()?;
+        let start_pos = reader.stream_position()?;
+        let end_pos = reader.seek(std::io::SeekFrom::End(0))?;
+        reader.seek(std::io::SeekFrom::Start(start_pos))?;
+
+        let folder_count = reader.read_u32::<BigEndian>()?;
 
         for _ in 0..folder_count {
-            let file_count = self.cursor.read_u8()?;
+            let file_count = reader.read_u8()?;
             self.folders.push(PackManFolder::new(file_count));
         }
 
-        let aligned_next_pos = Alignment::A4(self.cursor.position()).unwrap();
-        self.cursor
-            .seek(std::io::SeekFrom::Start(aligned_next_pos))?;
+        let aligned_next_pos = Alignment::A4(reader.stream_position()? - start_pos).unwrap();
+        reader.seek(std::io::SeekFrom::Start(start_pos + aligned_next_pos))?;
 
         // Skip the starting file indices for each folder (unnecessary info)
-        self.cursor.seek_relative(
+        reader.seek_relative(
             (size_of::<u16>() * folder_count as usize)
                 .try_into()
                 .unwrap(),
         )?;
 
         for i in 0..folder_count {
-            let folder_id = self.cursor.read_u16::<BigEndian>()?;
+            let folder_id = reader.read_u16::<BigEndian>()?;
             let folder = &mut self.folders[i as usize];
             folder.id = folder_id;
             folder.is_id_valid = true;
@@ -122,7 +156,7 @@ impl PackManArchive {
         let mut cur_file_count = 0;
         for folder in &mut self.folders {
             for _ in 0..folder.file_count {
-                let offset = self.cursor.read_u32::<BigEndian>()?;
+                let offset = reader.read_u32::<BigEndian>()?;
                 cur_file_count += 1;
 
                 if offset == 0 {
@@ -131,13 +165,13 @@ impl PackManArchive {
                     continue;
                 }
 
-                let next_file_offset = self.cursor.position();
+                let next_file_offset = reader.stream_position()?;
                 let mut next_nonzero_offset = None;
                 let mut cur_count_copy = cur_file_count;
 
                 // Find the next non-zero offset to calculate file size
                 while cur_count_copy < file_count && next_nonzero_offset.is_none() {
-                    let next_offset = self.cursor.read_u32::<BigEndian>()?;
+                    let next_offset = reader.read_u32::<BigEndian>()?;
                     cur_count_copy += 1;
 
                     if next_offset != 0 {
@@ -146,25 +180,73 @@ impl PackManArchive {
                 }
 
                 if next_nonzero_offset.is_none() {
-                    next_nonzero_offset = Some(self.cursor.get_ref().len().try_into().unwrap());
+                    next_nonzero_offset = Some((end_pos - start_pos).try_into().unwrap());
                 }
 
                 let file_size = next_nonzero_offset.unwrap() - offset;
 
                 // Read file
                 let mut buf = vec![0; file_size.try_into().unwrap()];
-                self.cursor.seek(std::io::SeekFrom::Start(offset.into()))?;
-                self.cursor.read_exact(&mut buf)?;
+                reader.seek(std::io::SeekFrom::Start(start_pos + u64::from(offset)))?;
+                reader.read_exact(&mut buf)?;
                 folder.files.push(PackManFile::new(buf));
 
-                self.cursor
-                    .seek(std::io::SeekFrom::Start(next_file_offset))?;
+                reader.seek(std::io::SeekFrom::Start(next_file_offset))?;
             }
         }
 
         Ok(())
     }
 
+    /// Parses every file in this archive that's a texture archive. Files that aren't texture
+    /// archives are skipped.
+    pub fn texture_archives(&self) -> Vec<TextureArchive> {
+        self.folders
+            .iter()
+            .flat_map(|folder| &folder.files)
+            .filter_map(|f| TextureArchive::from_bytes(&f.data).ok())
+            .collect()
+    }
+
+    /// Exports the textures of every texture archive in this archive to a folder, given by
+    /// `path`, as a Dolphin custom texture pack. See [`dolphin::export_dolphin_pack()`] for more
+    /// details.
+    pub fn export_dolphin_pack(&self, path: &Path) -> std::io::Result<DolphinPackReport> {
+        let textures: Vec<_> = self
+            .texture_archives()
+            .into_iter()
+            .flat_map(|archive| archive.entries)
+            .map(|entry| entry.texture)
+            .collect();
+
+        dolphin::export_dolphin_pack(&textures, path)
+    }
+
+    /// Replaces the textures in every texture archive in this archive that have an image in the
+    /// given Dolphin custom texture `pack`. Only the files of texture archives that had textures
+    /// replaced are rewritten.
+    pub fn import_dolphin_pack(
+        &mut self,
+        pack: &DolphinTexturePack,
+    ) -> std::io::Result<DolphinPackReport> {
+        let mut report = DolphinPackReport::default();
+
+        for f in self.folders.iter_mut().flat_map(|folder| &mut folder.files) {
+            let Ok(mut archive) = TextureArchive::from_bytes(&f.data) else {
+                continue;
+            };
+
+            let archive_report = archive.import_dolphin_pack(pack)?;
+            if !archive_report.processed.is_empty() {
+                f.data = archive.to_bytes();
+            }
+
+            report.merge(archive_report);
+        }
+
+        Ok(report)
+    }
+
     /// Gets the count of all the files from each folder in the archive.
     /// Only used when reading an archive via [`PackManArchive::read()`], and all folders have been instantiated.
     fn get_all_file_count(&self) -> usize {
@@ -2,13 +2,232 @@
 
 use crate::util::Alignment;
 
-use super::gvr_texture::GVRTexture;
+use super::gvr_texture::{
+    constraints::GVRConstraintViolation,
+    diff::{self, GVRTextureMatch},
+    dolphin::{self, DolphinPackReport, DolphinTexturePack},
+    GVRError, GVRTexture,
+};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::{
-    fs::File,
-    io::{BufRead, Cursor, Seek, SeekFrom, Write},
+    fmt,
+    io::{Cursor, Read, Seek, SeekFrom},
 };
 
+/// An error that occurred while reading a [`TextureArchive`]. All offsets are relative to the
+/// start of the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureArchiveError {
+    /// The archive ended before a field could be read.
+    Truncated {
+        /// The name of the field.
+        field: &'static str,
+        /// The offset of the field.
+        offset: u64,
+    },
+    /// A header field contains a value that isn't valid for it.
+    BadValue {
+        /// The name of the field.
+        field: &'static str,
+        /// The offset of the field.
+        offset: u64,
+        /// The value that was found.
+        value: u32,
+    },
+    /// A texture name contains characters that aren't printable ASCII.
+    InvalidName {
+        /// The index of the texture in the archive.
+        index: usize,
+        /// The offset of the name.
+        offset: u64,
+    },
+    /// A texture offset points past the end of the archive.
+    OffsetOutOfBounds {
+        /// The index of the texture in the archive.
+        index: usize,
+        /// The offset of the texture offset field.
+        offset: u64,
+        /// The offset of the texture that was found.
+        texture_offset: u32,
+        /// The size of the archive.
+        archive_size: u64,
+    },
+    /// One of the textures in the archive couldn't be parsed.
+    InvalidTexture {
+        /// The index of the texture in the archive.
+        index: usize,
+        /// The name of the texture.
+        name: String,
+        /// The offset of the texture.
+        offset: u64,
+        /// The reason the texture couldn't be parsed. Its offsets are relative to the start of
+        /// the texture.
+        source: GVRError,
+    },
+}
+
+impl TextureArchiveError {
+    fn truncated(field: &'static str, offset: u64) -> Self {
+        TextureArchiveError::Truncated { field, offset }
+    }
+
+    /// Returns the offset the error is about, relative to the start of the archive. For invalid
+    /// textures, this is the offset of the invalid field inside the texture.
+    pub fn offset(&self) -> u64 {
+        match self {
+            TextureArchiveError::Truncated { offset, .. }
+            | TextureArchiveError::BadValue { offset, .. }
+            | TextureArchiveError::InvalidName { offset, .. }
+            | TextureArchiveError::OffsetOutOfBounds { offset, .. } => *offset,
+            TextureArchiveError::InvalidTexture { offset, source, .. } => offset + source.offset(),
+        }
+    }
+}
+
+impl fmt::Display for TextureArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureArchiveError::Truncated { field, offset } => write!(
+                f,
+                "The texture archive is truncated at the {field} field (offset {offset:#x})."
+            ),
+            TextureArchiveError::BadValue {
+                field,
+                offset,
+                value,
+            } => write!(
+                f,
+                "Invalid {field} value {value:#x} at offset {offset:#x}. This is most likely not a texture archive."
+            ),
+            TextureArchiveError::InvalidName { index, offset } => write!(
+                f,
+                "The name of texture {index} at offset {offset:#x} isn't valid. This is most likely not a texture archive."
+            ),
+            TextureArchiveError::OffsetOutOfBounds {
+                index,
+                offset,
+                texture_offset,
+                archive_size,
+            } => write!(
+                f,
+                "The offset of texture {index} at offset {offset:#x} points to {texture_offset:#x}, past the end of the archive ({archive_size:#x} bytes)."
+            ),
+            TextureArchiveError::InvalidTexture {
+                index,
+                name,
+                offset,
+                source,
+            } => write!(
+                f,
+                "Texture {index} (\"{name}\") at offset {offset:#x} is invalid: {source}."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureArchiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureArchiveError::InvalidTexture { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// How damaged entries are handled while reading a [`TextureArchive`]. See
+/// [`TextureArchive::read_from_with_mode()`] for more details.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TextureArchiveReadMode {
+    /// Fail the read on the first damaged entry.
+    #[default]
+    Strict,
+    /// Keep damaged entries as raw bytes, and keep reading.
+    Lenient,
+}
+
+impl TextureArchiveReadMode {
+    /// Fails with `err` in strict mode, or adds it to `diagnostics` in lenient mode.
+    fn handle(
+        self,
+        err: TextureArchiveError,
+        diagnostics: &mut Vec<TextureArchiveError>,
+    ) -> Result<(), TextureArchiveError> {
+        match self {
+            TextureArchiveReadMode::Strict => Err(err),
+            TextureArchiveReadMode::Lenient => {
+                diagnostics.push(err);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Describes everything that was found wrong with a [`TextureArchive`] that was still read
+/// successfully. See [`TextureArchive::read_from_with_mode()`] for more details.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TextureArchiveReadReport {
+    /// The errors of the damaged entries that were kept in
+    /// [`TextureArchiveReadMode::Lenient`] mode. This is always empty in
+    /// [`TextureArchiveReadMode::Strict`] mode.
+    pub diagnostics: Vec<TextureArchiveError>,
+    /// The GX constraints broken by the textures that were read. See
+    /// [`TextureArchive::check_constraints()`] for more details.
+    pub violations: Vec<(usize, Vec<GVRConstraintViolation>)>,
+}
+
+/// The per-texture flags given to textures that weren't read out of a texture archive. See
+/// [`TextureArchiveEntry::flags`].
+pub const DEFAULT_ARCHIVE_FLAGS: u8 = 0x11;
+
+/// A texture of a texture archive, along with what the archive stores for it besides the GVR
+/// file itself. Replacing [`TextureArchiveEntry::texture`] keeps the rest as it is.
+#[derive(Clone)]
+pub struct TextureArchiveEntry {
+    /// The texture itself.
+    pub texture: GVRTexture,
+    /// The flags stored for this texture in a texture archive that isn't associated with a
+    /// model. See [`ARCHIVE_FLAG_NAMES`] for what's known about them.
+    pub flags: u8,
+    /// The bytes that followed this texture in the texture archive it was read from, up to the
+    /// next entry. These are written back after the texture, so that untouched archives are
+    /// exported exactly as they were read.
+    pub padding: Vec<u8>,
+}
+
+impl TextureArchiveEntry {
+    /// Creates a new entry for `texture`, with the default flags and without any padding.
+    pub fn new(texture: GVRTexture) -> Self {
+        Self {
+            texture,
+            flags: DEFAULT_ARCHIVE_FLAGS,
+            padding: vec![],
+        }
+    }
+}
+
+/// An entry of a texture archive that couldn't be read as a texture in
+/// [`TextureArchiveReadMode::Lenient`] mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DamagedTextureEntry {
+    /// The index of the entry in the archive it was read from. The entry is written back at this
+    /// index, or after the last texture if there aren't as many textures anymore.
+    pub index: usize,
+    /// The name of the entry.
+    pub name: String,
+    /// The per-texture flags of the entry. See [`TextureArchiveEntry::flags`].
+    pub archive_flags: u8,
+    /// The offset of the entry in the archive it was read from. Entries without any data are
+    /// written back with this offset unchanged, so they don't point at any other entry.
+    pub offset: u32,
+    /// The raw bytes of the entry, up to the next entry in the archive. This is empty if the
+    /// offset of the entry points past the end of the archive.
+    pub data: Vec<u8>,
+}
+
+/// The names of the known bits of the per-texture flags in texture archives (see
+/// [`TextureArchiveEntry::flags`]), as `(mask, name)` pairs. What the other bits do isn't known yet.
+pub const ARCHIVE_FLAG_NAMES: &[(u8, &str)] = &[];
+
 /// Represents a GVR texture archive, used by Sonic Riders in any place textures are needed/used.
 #[derive(Default)]
 pub struct TextureArchive {
@@ -21,14 +240,26 @@ pub struct TextureArchive {
     /// Only used during reading a texture archive.
     texture_num: u16,
     /// Indicates whether this texture archive is associated with a 3D model, or if it's just a
-    /// general texture archive. If this is `true`, the exported file will also contain a bunch of
-    /// flags for each texture (`0x11`).
+    /// general texture archive. If this is `true`, the archive also contains a byte of flags for
+    /// each texture, which are kept in [`TextureArchiveEntry::flags`].
     pub is_without_model: bool,
 
     /// Only used during reading a texture archive.
     gvr_offsets: Vec<u32>,
-    /// Contains all the GVR textures in this archive.
-    pub textures: Vec<GVRTexture>,
+    /// Contains all the GVR textures in this archive, along with their flags and padding.
+    pub entries: Vec<TextureArchiveEntry>,
+    /// Contains the entries that couldn't be read as textures, in the order of their indices.
+    /// These are only kept when reading in [`TextureArchiveReadMode::Lenient`] mode, and are
+    /// written back as is.
+    pub damaged_entries: Vec<DamagedTextureEntry>,
+
+    /// The size of the header and the name table when the archive was read.
+    read_name_table_size: usize,
+    /// The padding between the name table and the first texture when the archive was read. It's
+    /// only written back as long as the name table keeps the same size.
+    name_table_padding: Vec<u8>,
+    /// The data after the last texture of the archive, which is written back as is.
+    pub trailing_data: Vec<u8>,
 }
 
 impl TextureArchive {
@@ -51,73 +282,320 @@ impl TextureArchive {
         Default::default()
     }
 
+    /// Creates a new [`TextureArchive`] by reading the archive from `reader`, starting at its
+    /// current position. See [`TextureArchive::read_from()`] for more details.
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self, TextureArchiveError> {
+        let mut archive = Self::new_empty();
+        archive.read_from(reader)?;
+        Ok(archive)
+    }
+
+    /// Creates a new [`TextureArchive`] by reading the archive at the start of `data`. See
+    /// [`TextureArchive::read_from()`] for more details.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, TextureArchiveError> {
+        Self::from_reader(&mut Cursor::new(data))
+    }
+
+    /// Creates a new [`TextureArchive`] by reading the archive at the start of `data` in the
+    /// given `mode`, along with the report of the read. See
+    /// [`TextureArchive::read_from_with_mode()`] for more details.
+    pub fn from_bytes_with_mode(
+        data: &[u8],
+        mode: TextureArchiveReadMode,
+    ) -> Result<(Self, TextureArchiveReadReport), TextureArchiveError> {
+        let mut archive = Self::new_empty();
+        let report = archive.read_from_with_mode(&mut Cursor::new(data), mode)?;
+        Ok((archive, report))
+    }
+
     /// Reads the contents of the archive, constructed with [`TextureArchive::new()`].
     ///
+    /// See [`TextureArchive::read_from()`] for more details.
+    pub fn read(&mut self) -> Result<(), TextureArchiveError> {
+        self.read_with_mode(TextureArchiveReadMode::Strict)
+            .map(|_| ())
+    }
+
+    /// Reads the contents of the archive, constructed with [`TextureArchive::new()`], in the
+    /// given `mode`.
+    ///
+    /// See [`TextureArchive::read_from_with_mode()`] for more details.
+    pub fn read_with_mode(
+        &mut self,
+        mode: TextureArchiveReadMode,
+    ) -> Result<TextureArchiveReadReport, TextureArchiveError> {
+        let mut cursor = std::mem::take(&mut self.cursor);
+        let result = self.read_from_with_mode(&mut cursor, mode);
+        self.cursor = cursor;
+
+        result
+    }
+
+    /// Reads the contents of the archive from `reader`, starting at its current position. All
+    /// offsets in the archive are relative to that position, so the archive can be read straight
+    /// out of a bigger file. Everything after the last texture up to the end of `reader` is kept
+    /// in [`TextureArchive::trailing_data`].
+    ///
+    /// Everything else that isn't a part of a texture, like the per-texture flags and the padding
+    /// between textures, is kept as well. Exporting an archive that hasn't been changed produces
+    /// the exact same file, as long as its textures are stored in the same order as their names
+    /// and are GCIX-headed.
+    ///
     /// This function performs validity checks on the file, checking if it's a valid GVR texture
-    /// archive file. It also checks if the textures in the archive are valid.
-    pub fn read(&mut self) -> Result<(), &str> {
-        self.texture_num = self.cursor.read_u16::<BigEndian>().unwrap();
-        let is_without_model = self.cursor.read_u16::<BigEndian>().unwrap();
+    /// archive file. It also checks if the textures in the archive are valid, failing on the first
+    /// texture that isn't. Every read is bounds-checked, so a malformed or truncated archive
+    /// results in an error describing where reading failed.
+    pub fn read_from<R: Read + Seek>(&mut self, reader: &mut R) -> Result<(), TextureArchiveError> {
+        self.read_from_with_mode(reader, TextureArchiveReadMode::Strict)
+            .map(|_| ())
+    }
+
+    /// Reads the contents of the archive from `reader` like [`TextureArchive::read_from()`], in
+    /// the given `mode`. The textures that were read are also checked against the constraints of
+    /// the GX hardware, which doesn't fail the read in either mode.
+    ///
+    /// In [`TextureArchiveReadMode::Lenient`] mode, damaged entries don't fail the read. Instead,
+    /// they're kept in [`TextureArchive::damaged_entries`], and the errors that would have failed
+    /// the read in [`TextureArchiveReadMode::Strict`] mode are returned as diagnostics. Errors in
+    /// the header or the name table, which the layout of the whole archive depends on, fail the
+    /// read in both modes.
+    pub fn read_from_with_mode<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        mode: TextureArchiveReadMode,
+    ) -> Result<TextureArchiveReadReport, TextureArchiveError> {
+        let truncated = TextureArchiveError::truncated;
+        let mut diagnostics = vec![];
+        self.gvr_offsets.clear();
+        self.entries.clear();
+        self.damaged_entries.clear();
+
+        let start_pos = reader
+            .stream_position()
+            .map_err(|_| truncated("texture count", 0))?;
+        let archive_size = reader
+            .seek(SeekFrom::End(0))
+            .and_then(|end| reader.seek(SeekFrom::Start(start_pos)).map(|_| end))
+            .map_err(|_| truncated("texture count", 0))?
+            .saturating_sub(start_pos);
+
+        self.texture_num = reader
+            .read_u16::<BigEndian>()
+            .map_err(|_| truncated("texture count", 0))?;
+        let is_without_model = reader
+            .read_u16::<BigEndian>()
+            .map_err(|_| truncated("archive kind", 2))?;
 
         if is_without_model > 1 {
-            return Err("This is an invalid texture archive!");
+            return Err(TextureArchiveError::BadValue {
+                field: "archive kind",
+                offset: 2,
+                value: is_without_model.into(),
+            });
         }
 
         self.is_without_model = is_without_model == 1;
 
         // Read all offsets to the textures in the file
-        for _ in 0..self.texture_num {
-            self.gvr_offsets
-                .push(self.cursor.read_u32::<BigEndian>().unwrap());
+        for i in 0..usize::from(self.texture_num) {
+            let offset = 4 + i as u64 * 4;
+            let texture_offset = reader
+                .read_u32::<BigEndian>()
+                .map_err(|_| truncated("texture offset", offset))?;
+
+            if u64::from(texture_offset) >= archive_size {
+                mode.handle(
+                    TextureArchiveError::OffsetOutOfBounds {
+                        index: i,
+                        offset,
+                        texture_offset,
+                        archive_size,
+                    },
+                    &mut diagnostics,
+                )?;
+            }
+
+            self.gvr_offsets.push(texture_offset);
         }
 
-        // Skip flags if necessary
+        // Read the per-texture flags, if the archive has them
+        let mut texture_flags = vec![DEFAULT_ARCHIVE_FLAGS; self.texture_num.into()];
         if self.is_without_model {
-            let _ = self.cursor.seek_relative(self.texture_num.into()); // TODO: implement EOF check
+            let offset = 4 + u64::from(self.texture_num) * 4;
+            reader
+                .read_exact(&mut texture_flags)
+                .map_err(|_| truncated("texture flags", offset))?;
         }
 
-        // Read all texture names in the file
-        for i in 0..self.texture_num {
-            let mut buf: Vec<u8> = vec![];
-            let _ = self.cursor.read_until(0x00, &mut buf); // TODO: implement EOF check
+        // The data of an entry goes up to the start of the next one in the file
+        let entry_starts: Vec<u64> = self
+            .gvr_offsets
+            .iter()
+            .map(|&o| u64::from(o))
+            .filter(|&o| o < archive_size)
+            .collect();
+        let next_entry = |pos: u64| {
+            entry_starts
+                .iter()
+                .copied()
+                .filter(|&o| o > pos)
+                .min()
+                .unwrap_or(archive_size)
+        };
 
-            // Pop delimiter byte
-            buf.pop();
+        // Read all texture names in the file
+        let mut texture_ends = Vec::with_capacity(self.texture_num.into());
+        let mut data_end = 0;
+        for (i, &flags) in texture_flags.iter().enumerate() {
+            let name_pos = reader
+                .stream_position()
+                .map_err(|_| truncated("texture name", 0))?;
+            let name_offset = name_pos - start_pos;
+            let buf = read_name(reader).map_err(|_| truncated("texture name", name_offset))?;
 
             let ascii_buf: Vec<char> = buf.into_iter().map(|e| e as char).collect();
+            let is_valid_char = |e: &char| e.is_ascii_graphic() || e.is_ascii_whitespace();
 
-            if !ascii_buf
-                .iter()
-                .all(|&e| e.is_ascii_graphic() || e.is_ascii_whitespace())
-            {
-                return Err("Can't read texture file names. This is most likely an invalid texture archive.");
+            if !ascii_buf.iter().all(is_valid_char) {
+                mode.handle(
+                    TextureArchiveError::InvalidName {
+                        index: i,
+                        offset: name_offset,
+                    },
+                    &mut diagnostics,
+                )?;
             }
 
-            let tex_name: String = ascii_buf.into_iter().collect();
+            let tex_name: String = ascii_buf
+                .into_iter()
+                .map(|e| if is_valid_char(&e) { e } else { '?' })
+                .collect();
 
-            let last_pos = self.cursor.position();
-            if self
-                .cursor
-                .seek(SeekFrom::Start(self.gvr_offsets[i as usize].into()))
-                .is_err()
-            {
-                return Err("Something went wrong reading the texture archive.");
+            let tex_offset = u64::from(self.gvr_offsets[i]);
+            if tex_offset >= archive_size {
+                self.damaged_entries.push(DamagedTextureEntry {
+                    index: i,
+                    name: tex_name,
+                    archive_flags: flags,
+                    offset: self.gvr_offsets[i],
+                    data: vec![],
+                });
+                continue;
             }
 
-            if let Ok(tex) = GVRTexture::new_from_cursor(tex_name, &mut self.cursor) {
-                self.textures.push(tex);
+            let last_pos = reader
+                .stream_position()
+                .map_err(|_| truncated("texture name", name_offset))?;
+            let invalid_texture = |source| TextureArchiveError::InvalidTexture {
+                index: i,
+                name: tex_name.clone(),
+                offset: tex_offset,
+                source,
+            };
+
+            let texture = reader
+                .seek(SeekFrom::Start(start_pos + tex_offset))
+                .map_err(|_| truncated_magic())
+                .and_then(|_| GVRTexture::new_from_reader(tex_name.clone(), reader));
+
+            match texture {
+                Ok(texture) => {
+                    self.entries.push(TextureArchiveEntry {
+                        texture,
+                        flags,
+                        padding: vec![],
+                    });
+
+                    let tex_end = reader
+                        .stream_position()
+                        .map_err(|_| truncated("texture data", tex_offset))?
+                        - start_pos;
+                    texture_ends.push(tex_end);
+                    data_end = data_end.max(tex_end);
+                }
+                Err(source) => {
+                    mode.handle(invalid_texture(source), &mut diagnostics)?;
+
+                    let entry_end = next_entry(tex_offset);
+                    let data = read_range(reader, start_pos + tex_offset, entry_end - tex_offset)
+                        .map_err(|_| truncated("texture data", tex_offset))?;
+                    self.damaged_entries.push(DamagedTextureEntry {
+                        index: i,
+                        name: tex_name,
+                        archive_flags: flags,
+                        offset: self.gvr_offsets[i],
+                        data,
+                    });
+                    data_end = data_end.max(entry_end);
+                }
             }
 
-            let _ = self.cursor.seek(SeekFrom::Start(last_pos));
+            reader
+                .seek(SeekFrom::Start(last_pos))
+                .map_err(|_| truncated("texture name", name_offset))?;
         }
 
-        #[cfg(debug_assertions)]
-        self.debug_print();
+        let name_table_end = reader
+            .stream_position()
+            .map_err(|_| truncated("texture name", 0))?
+            - start_pos;
+
+        self.read_padding(
+            reader,
+            start_pos,
+            name_table_end,
+            &texture_ends,
+            data_end.max(name_table_end).min(archive_size),
+            archive_size,
+        )
+        .map_err(|_| truncated("padding", name_table_end))?;
 
-        if !self.validate_textures() {
-            return Err("The textures in this archive are not valid.");
+        Ok(TextureArchiveReadReport {
+            diagnostics,
+            violations: self.check_constraints(),
+        })
+    }
+
+    /// Reads everything that's between the name table and the textures, between the textures
+    /// themselves and after the last entry, where `name_table_end`, `texture_ends` and `data_end`
+    /// are the offsets those end at.
+    ///
+    /// The padding after a texture goes up to the next entry stored in the file, and is only
+    /// written back in the right place if the textures are stored in the same order as their
+    /// names.
+    fn read_padding<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        start_pos: u64,
+        name_table_end: u64,
+        texture_ends: &[u64],
+        data_end: u64,
+        archive_end: u64,
+    ) -> std::io::Result<()> {
+        let offsets: Vec<u64> = self
+            .gvr_offsets
+            .iter()
+            .map(|&o| u64::from(o))
+            .filter(|&o| o < archive_end)
+            .collect();
+        let next_entry = |pos: u64| offsets.iter().copied().filter(|&o| o >= pos).min();
+        let mut read_between =
+            |start: u64, end: u64| read_range(reader, start_pos + start, end - start);
+
+        self.read_name_table_size = name_table_end as usize;
+        self.name_table_padding = match offsets.iter().min() {
+            Some(&first) if first >= name_table_end => read_between(name_table_end, first)?,
+            _ => vec![],
+        };
+
+        for (entry, &end) in self.entries.iter_mut().zip(texture_ends) {
+            if let Some(next) = next_entry(end) {
+                entry.padding = read_between(end, next)?;
+            }
         }
 
+        self.trailing_data = read_between(data_end, archive_end)?;
+
         Ok(())
     }
 
@@ -125,53 +603,75 @@ impl TextureArchive {
     /// given in `path`.
     ///
     /// Any textures in this archive that do not have a name will be named "unnamed" in the
-    /// resulting file.
-    pub fn export(&self, path: &str) -> std::io::Result<()> {
-        let mut file = File::create(path)?;
+    /// resulting file. The padding and trailing data read along with the archive are written back
+    /// in place, see [`TextureArchive::read_from()`] for more details. So are the
+    /// [`TextureArchive::damaged_entries`].
+    ///
+    /// The textures are checked against the constraints of the GX hardware as well. Textures that
+    /// break them are still exported, and are returned along with the constraints they break.
+    /// See [`TextureArchive::check_constraints()`] for more details.
+    pub fn export(&self, path: &str) -> std::io::Result<Vec<(usize, Vec<GVRConstraintViolation>)>> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(self.check_constraints())
+    }
+
+    /// Serializes all the textures in this archive into the properly formatted binary file. See
+    /// [`TextureArchive::export()`] for more details. Unlike exporting, this doesn't check the
+    /// textures against the constraints of the GX hardware.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        let entries = self.written_entries();
 
-        file.write_u16::<BigEndian>(self.textures.len().try_into().unwrap())?;
-        file.write_u16::<BigEndian>(self.is_without_model.into())?;
+        buf.write_u16::<BigEndian>(entries.len().try_into().unwrap())
+            .unwrap();
+        buf.write_u16::<BigEndian>(self.is_without_model.into())
+            .unwrap();
 
-        let offsets = self.calculate_offset_table();
+        let offsets = self.calculate_offset_table(&entries);
 
         // Write offset table
         for offset in &offsets {
-            file.write_u32::<BigEndian>(*offset)?;
+            buf.write_u32::<BigEndian>(*offset).unwrap();
         }
 
         // Write flags if needed
         if self.is_without_model {
-            for _ in 0..self.textures.len() {
-                file.write_u8(0x11)?;
+            for entry in &entries {
+                buf.write_u8(entry.flags).unwrap();
             }
         }
 
         // Write texture names
-        for tex in &self.textures {
-            if tex.name.is_empty() {
-                file.write_all(b"unnamed")?;
-            } else {
-                file.write_all(tex.name.as_bytes())?;
-            }
-
-            file.write_u8(0)?; // null delimiter
+        for entry in &entries {
+            buf.extend_from_slice(entry.name);
+            buf.push(0); // null delimiter
         }
 
         // Padding
-        file.set_len(offsets[0].into())?;
-        file.seek(SeekFrom::Start(offsets[0].into()))?;
+        if buf.len() == self.read_name_table_size {
+            buf.extend_from_slice(&self.name_table_padding);
+        } else if let Some(first_offset) = entries
+            .iter()
+            .zip(&offsets)
+            .find(|(entry, _)| entry.fixed_offset.is_none())
+            .map(|(_, offset)| offset)
+        {
+            buf.resize(*first_offset as usize, 0);
+        }
 
         // Write texture data
-        for tex in &self.textures {
-            file.write_all(tex.data.get_ref())?;
+        for entry in &entries {
+            buf.extend_from_slice(entry.data);
+            buf.extend_from_slice(entry.padding);
         }
 
-        Ok(())
+        buf.extend_from_slice(&self.trailing_data);
+        buf
     }
 
     /// Extracts all the contained GVR textures in this archive to a folder, given by `path`.
     pub fn extract_all(&self, path: &std::path::Path) -> std::io::Result<()> {
-        for tex in &self.textures {
+        for tex in self.textures() {
             let filepath = path.join(format!("{}.gvr", tex.name));
             std::fs::write(filepath, tex.data.get_ref())?;
         }
@@ -179,69 +679,645 @@ impl TextureArchive {
         Ok(())
     }
 
-    fn calculate_first_tex_offset(&self) -> usize {
+    /// Extracts all the contained GVR textures in this archive to a folder, given by `path`, as
+    /// PNG images. Only the base texture of each GVR texture is extracted.
+    pub fn extract_all_as_png(&self, path: &std::path::Path) -> std::io::Result<()> {
+        for tex in self.textures() {
+            let png = tex
+                .to_png()
+                .map_err(|err| std::io::Error::other(format!("Texture \"{}\": {err}", tex.name)))?;
+
+            let filepath = path.join(format!("{}.png", tex.name));
+            std::fs::write(filepath, png)?;
+        }
+
+        Ok(())
+    }
+
+    /// Exports all the textures in this archive to a folder, given by `path`, as a Dolphin custom
+    /// texture pack. See [`dolphin::export_dolphin_pack()`] for more details.
+    pub fn export_dolphin_pack(
+        &self,
+        path: &std::path::Path,
+    ) -> std::io::Result<DolphinPackReport> {
+        dolphin::export_dolphin_pack(self.textures(), path)
+    }
+
+    /// Replaces all the textures in this archive that have an image in the given Dolphin custom
+    /// texture `pack`. See [`DolphinTexturePack::replace_textures()`] for more details.
+    pub fn import_dolphin_pack(
+        &mut self,
+        pack: &DolphinTexturePack,
+    ) -> std::io::Result<DolphinPackReport> {
+        pack.replace_textures(self.entries.iter_mut().map(|entry| &mut entry.texture))
+    }
+
+    /// Checks every texture in this archive against the constraints of the GX hardware. Returns
+    /// the index of every texture that breaks any of them, along with the constraints it breaks.
+    /// See [`GVRTexture::check_constraints()`] for more details.
+    pub fn check_constraints(&self) -> Vec<(usize, Vec<GVRConstraintViolation>)> {
+        self.textures()
+            .map(GVRTexture::check_constraints)
+            .enumerate()
+            .filter(|(_, violations)| !violations.is_empty())
+            .collect()
+    }
+
+    /// Compares the textures of this archive, as the old archive, with the textures of the `new`
+    /// archive. See [`diff::diff_textures()`] for how the textures are matched.
+    pub fn diff(&self, new: &TextureArchive) -> Vec<GVRTextureMatch> {
+        let old: Vec<&GVRTexture> = self.textures().collect();
+        let new: Vec<&GVRTexture> = new.textures().collect();
+        diff::diff_textures(&old, &new)
+    }
+
+    /// Returns an iterator over all the GVR textures in this archive, without their flags and
+    /// padding.
+    pub fn textures(&self) -> impl Iterator<Item = &GVRTexture> {
+        self.entries.iter().map(|entry| &entry.texture)
+    }
+
+    /// Returns the textures and the damaged entries of this archive in the order they're written.
+    fn written_entries(&self) -> Vec<WrittenEntry<'_>> {
+        let mut entries: Vec<WrittenEntry> = self
+            .entries
+            .iter()
+            .map(|entry| WrittenEntry {
+                name: written_name(&entry.texture.name),
+                flags: entry.flags,
+                data: entry.texture.data.get_ref(),
+                padding: &entry.padding,
+                fixed_offset: None,
+            })
+            .collect();
+
+        for entry in &self.damaged_entries {
+            let entry_index = entry.index.min(entries.len());
+            entries.insert(
+                entry_index,
+                WrittenEntry {
+                    name: written_name(&entry.name),
+                    flags: entry.archive_flags,
+                    data: &entry.data,
+                    padding: &[],
+                    fixed_offset: entry.data.is_empty().then_some(entry.offset),
+                },
+            );
+        }
+
+        entries
+    }
+
+    fn calculate_name_table_size(&self, entries: &[WrittenEntry]) -> usize {
         let mut result_offset = 4; // 4 bytes to account for start of file
-        let offset_table_size = self.textures.len() * size_of::<u32>();
+        let offset_table_size = entries.len() * size_of::<u32>();
 
         result_offset += offset_table_size;
 
         if self.is_without_model {
-            result_offset += self.textures.len();
+            result_offset += entries.len();
         }
 
         // Calculate length of each texture name, add it to the offset
-        for tex in &self.textures {
-            result_offset += tex.name.len() + 1; // extra byte for null delimiter
+        for entry in entries {
+            result_offset += entry.name.len() + 1; // extra byte for null delimiter
+        }
+
+        result_offset
+    }
+
+    fn calculate_first_tex_offset(&self, entries: &[WrittenEntry]) -> usize {
+        let name_table_size = self.calculate_name_table_size(entries);
+
+        // Keep the padding that was read, as long as it still fits
+        if name_table_size == self.read_name_table_size {
+            return name_table_size + self.name_table_padding.len();
         }
 
-        let aligned = Alignment::A32(result_offset);
+        let aligned = Alignment::A32(name_table_size);
         aligned.unwrap()
     }
 
-    fn calculate_offset_table(&self) -> Vec<u32> {
-        let mut offsets = Vec::with_capacity(self.textures.len());
-        let mut cur_offset = self.calculate_first_tex_offset() as u32;
+    fn calculate_offset_table(&self, entries: &[WrittenEntry]) -> Vec<u32> {
+        let mut offsets = Vec::with_capacity(entries.len());
+        let mut cur_offset = self.calculate_first_tex_offset(entries) as u32;
+
+        for entry in entries {
+            if let Some(offset) = entry.fixed_offset {
+                offsets.push(offset);
+                continue;
+            }
 
-        for tex in &self.textures {
             offsets.push(cur_offset);
-            cur_offset += tex.size;
+            cur_offset += (entry.data.len() + entry.padding.len()) as u32;
         }
 
         offsets
     }
+}
 
-    fn validate_textures(&mut self) -> bool {
-        for offset in &self.gvr_offsets {
-            if self.cursor.seek(SeekFrom::Start(*offset as u64)).is_err() {
-                return false;
-            }
+/// A texture or a damaged entry, as it's written into a texture archive.
+struct WrittenEntry<'a> {
+    name: &'a [u8],
+    flags: u8,
+    data: &'a [u8],
+    padding: &'a [u8],
+    /// The offset written for entries that don't have any data, instead of the offset of the
+    /// entry after them.
+    fixed_offset: Option<u32>,
+}
+
+/// Returns the bytes `name` is written as into a texture archive, which is "unnamed" for entries
+/// without a name.
+fn written_name(name: &str) -> &[u8] {
+    if name.is_empty() {
+        b"unnamed"
+    } else {
+        name.as_bytes()
+    }
+}
+
+/// Reads `len` bytes from `reader`, starting at the absolute position `pos`.
+fn read_range<R: Read + Seek>(reader: &mut R, pos: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(pos))?;
+    let mut buf = vec![0; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// The error for a texture that can't even be seeked to.
+fn truncated_magic() -> GVRError {
+    GVRError::TruncatedHeader {
+        field: "magic",
+        offset: 0,
+    }
+}
+
+/// Reads a null-terminated string from `reader`, without the null delimiter. Fails if `reader`
+/// runs out of data before the null delimiter.
+fn read_name<R: Read>(reader: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![];
 
-            if GVRTexture::validate(&mut self.cursor).is_err() {
-                return false;
+    loop {
+        let byte = reader.read_u8()?;
+        if byte == 0x00 {
+            return Ok(buf);
+        }
+
+        buf.push(byte);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riders::gvr_texture::{encode::GVREncodeOptions, image::RGBAImage, GVRDataFormat};
+
+    fn texture_data(color: [u8; 4]) -> Vec<u8> {
+        let mut image = RGBAImage::new(8, 8);
+        for y in 0..8 {
+            for x in 0..8 {
+                image.set_pixel(x, y, color);
             }
+        }
+
+        let options = GVREncodeOptions::new(GVRDataFormat::RGB565);
+        let tex = GVRTexture::encode("".into(), &image, &options).unwrap();
+        tex.data.into_inner()
+    }
+
+    /// A texture of a synthetic archive, with the bytes that follow it.
+    struct Entry {
+        name: &'static str,
+        flags: u8,
+        data: Vec<u8>,
+        padding: Vec<u8>,
+    }
+
+    /// Builds an archive by hand, placing every texture right after the previous one's padding.
+    fn build_archive(
+        without_model: bool,
+        entries: &[Entry],
+        name_table_padding: &[u8],
+        trailing_data: &[u8],
+    ) -> Vec<u8> {
+        let mut names = vec![];
+        for entry in entries {
+            names.extend_from_slice(entry.name.as_bytes());
+            names.push(0);
+        }
+
+        let flags_len = if without_model { entries.len() } else { 0 };
+        let mut offset = 4 + entries.len() * 4 + flags_len + names.len() + name_table_padding.len();
 
-            let tex_size = GVRTexture::read_texture_size(&mut self.cursor).unwrap();
-            println!("texture size: {tex_size}");
+        let mut buf = vec![];
+        buf.write_u16::<BigEndian>(entries.len() as u16).unwrap();
+        buf.write_u16::<BigEndian>(without_model.into()).unwrap();
+        for entry in entries {
+            buf.write_u32::<BigEndian>(offset as u32).unwrap();
+            offset += entry.data.len() + entry.padding.len();
         }
+        if without_model {
+            buf.extend(entries.iter().map(|entry| entry.flags));
+        }
+        buf.extend_from_slice(&names);
+        buf.extend_from_slice(name_table_padding);
+        for entry in entries {
+            buf.extend_from_slice(&entry.data);
+            buf.extend_from_slice(&entry.padding);
+        }
+        buf.extend_from_slice(trailing_data);
+
+        buf
+    }
+
+    fn unusual_archive() -> Vec<u8> {
+        let entries = [
+            Entry {
+                name: "tex_a",
+                flags: 0x01,
+                data: texture_data([0xFF, 0, 0, 0xFF]),
+                padding: vec![0xAB; 32],
+            },
+            Entry {
+                name: "tex_b",
+                flags: 0x80,
+                data: texture_data([0, 0xFF, 0, 0xFF]),
+                padding: vec![],
+            },
+            Entry {
+                name: "tex_c",
+                flags: 0x11,
+                data: texture_data([0, 0, 0xFF, 0xFF]),
+                // Anything after the last texture is trailing data
+                padding: vec![],
+            },
+        ];
+
+        // 37 bytes of header, flags and names, padded to 96 bytes with non-zero filler
+        build_archive(true, &entries, &[0xEE; 59], b"trailing\0")
+    }
+
+    #[test]
+    fn round_trip_with_model() {
+        let entries = [
+            Entry {
+                name: "body",
+                flags: 0,
+                data: texture_data([0x80, 0x40, 0x20, 0xFF]),
+                padding: vec![],
+            },
+            Entry {
+                name: "face",
+                flags: 0,
+                data: texture_data([0x10, 0x20, 0x30, 0xFF]),
+                padding: vec![],
+            },
+        ];
+        // 4 + 8 + 10 bytes of header and names
+        let input = build_archive(false, &entries, &[0; 10], &[]);
+
+        let archive = TextureArchive::from_bytes(&input).unwrap();
+        assert!(!archive.is_without_model);
+        assert_eq!(archive.entries.len(), 2);
+        assert_eq!(archive.entries[1].texture.name, "face");
+        assert_eq!(archive.to_bytes(), input);
+    }
+
+    #[test]
+    fn round_trip_without_model() {
+        let input = unusual_archive();
+
+        let archive = TextureArchive::from_bytes(&input).unwrap();
+        assert!(archive.is_without_model);
+        let flags: Vec<u8> = archive.entries.iter().map(|e| e.flags).collect();
+        assert_eq!(flags, [0x01, 0x80, 0x11]);
+        assert_eq!(archive.entries[0].padding, [0xAB; 32]);
+        assert!(archive.entries[1].padding.is_empty());
+        assert_eq!(archive.trailing_data, b"trailing\0");
+        assert_eq!(archive.to_bytes(), input);
+    }
+
+    #[test]
+    fn round_trip_empty_archive() {
+        let input = [0, 0, 0, 1, 0xFF, 0xFF];
 
-        true
+        let archive = TextureArchive::from_bytes(&input).unwrap();
+        assert!(archive.entries.is_empty());
+        assert_eq!(archive.to_bytes(), input);
     }
 
-    #[cfg(debug_assertions)]
-    fn debug_print(&self) {
-        println!("File: {}", self.file_path);
+    #[test]
+    fn edited_archive_is_rebuilt() {
+        let input = unusual_archive();
+        let mut archive = TextureArchive::from_bytes(&input).unwrap();
+        archive.entries[1].texture.name = "a_much_longer_texture_name".into();
 
-        println!(
-            "texture_num: {}, is_without_model: {}",
-            self.texture_num, self.is_without_model
+        let output = archive.to_bytes();
+        let first_offset = u32::from_be_bytes(output[4..8].try_into().unwrap());
+        assert_eq!(first_offset % 32, 0);
+        assert!(output.ends_with(b"trailing\0"));
+
+        let rebuilt = TextureArchive::from_bytes(&output).unwrap();
+        assert_eq!(rebuilt.entries.len(), 3);
+        for (old, new) in archive.entries.iter().zip(&rebuilt.entries) {
+            assert_eq!(old.texture.name, new.texture.name);
+            assert_eq!(old.flags, new.flags);
+            assert_eq!(old.texture.data.get_ref(), new.texture.data.get_ref());
+            assert_eq!(old.padding, new.padding);
+        }
+    }
+
+    #[test]
+    fn replaced_texture_keeps_flags_and_padding() {
+        let input = unusual_archive();
+        let mut archive = TextureArchive::from_bytes(&input).unwrap();
+        let mut replacement = archive.entries[2].texture.clone();
+        replacement.name = archive.entries[0].texture.name.clone();
+        archive.entries[0].texture = replacement;
+
+        let rebuilt = TextureArchive::from_bytes(&archive.to_bytes()).unwrap();
+        assert_eq!(rebuilt.entries[0].flags, 0x01);
+        assert_eq!(rebuilt.entries[0].padding, [0xAB; 32]);
+        assert_eq!(
+            rebuilt.entries[0].texture.data.get_ref(),
+            archive.entries[2].texture.data.get_ref()
+        );
+    }
+
+    #[test]
+    fn unnamed_textures_keep_valid_offsets() {
+        let input = unusual_archive();
+        let mut archive = TextureArchive::from_bytes(&input).unwrap();
+        archive.entries[0].texture.name.clear();
+
+        let rebuilt = TextureArchive::from_bytes(&archive.to_bytes()).unwrap();
+        assert_eq!(rebuilt.entries[0].texture.name, "unnamed");
+        assert_eq!(
+            rebuilt.entries[0].texture.data.get_ref(),
+            archive.entries[0].texture.data.get_ref()
         );
+    }
+
+    #[test]
+    fn read_and_export_check_constraints() {
+        let options = GVREncodeOptions::new(GVRDataFormat::RGB565);
+        let odd = GVRTexture::encode("odd".into(), &RGBAImage::new(12, 4), &options).unwrap();
+        let entries = [
+            Entry {
+                name: "fine",
+                flags: 0,
+                data: texture_data([0, 0, 0, 0xFF]),
+                padding: vec![],
+            },
+            Entry {
+                name: "odd",
+                flags: 0,
+                data: odd.data.into_inner(),
+                padding: vec![],
+            },
+        ];
+        // 4 + 8 + 9 bytes of header and names
+        let input = build_archive(false, &entries, &[0; 11], &[]);
 
-        for offset in &self.gvr_offsets {
-            println!("{offset}");
+        let expected = vec![(
+            1,
+            vec![GVRConstraintViolation::NotPowerOfTwo {
+                width: 12,
+                height: 4,
+            }],
+        )];
+
+        let (archive, report) =
+            TextureArchive::from_bytes_with_mode(&input, TextureArchiveReadMode::Strict).unwrap();
+        assert!(report.diagnostics.is_empty());
+        assert_eq!(report.violations, expected);
+
+        let path = std::env::temp_dir().join(format!("texture-archive-{}", std::process::id()));
+        let violations = archive.export(&path.display().to_string()).unwrap();
+        assert_eq!(violations, expected);
+        assert_eq!(std::fs::read(&path).unwrap(), input);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A small xorshift generator, so the fuzz tests are reproducible without extra dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, max: usize) -> usize {
+            (self.next() % max as u64) as usize
         }
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let err = |data: &[u8]| TextureArchive::from_bytes(data).err().unwrap();
+
+        assert_eq!(
+            err(&[]),
+            TextureArchiveError::Truncated {
+                field: "texture count",
+                offset: 0
+            }
+        );
+        assert_eq!(
+            err(&[0, 1, 0, 2]),
+            TextureArchiveError::BadValue {
+                field: "archive kind",
+                offset: 2,
+                value: 2
+            }
+        );
+        assert_eq!(
+            err(&[0, 2, 0, 0, 0, 0, 0, 8]),
+            TextureArchiveError::OffsetOutOfBounds {
+                index: 0,
+                offset: 4,
+                texture_offset: 8,
+                archive_size: 8
+            }
+        );
+        assert_eq!(
+            err(&[0, 1, 0, 1, 0, 0, 0, 4]),
+            TextureArchiveError::Truncated {
+                field: "texture flags",
+                offset: 8
+            }
+        );
+        assert_eq!(
+            err(&[0, 1, 0, 0, 0, 0, 0, 8, b'a']),
+            TextureArchiveError::Truncated {
+                field: "texture name",
+                offset: 8
+            }
+        );
+        assert_eq!(
+            err(&[0, 1, 0, 0, 0, 0, 0, 8, 0xFF, 0]),
+            TextureArchiveError::InvalidName {
+                index: 0,
+                offset: 8
+            }
+        );
+
+        let invalid_texture = err(&[0, 1, 0, 0, 0, 0, 0, 8, b'a', 0]);
+        assert!(matches!(
+            &invalid_texture,
+            TextureArchiveError::InvalidTexture { index: 0, name, offset: 8, .. } if name == "a"
+        ));
+        assert_eq!(invalid_texture.offset(), 8);
+    }
 
-        for GVRTexture { name, .. } in &self.textures {
-            println!("{name}");
+    #[test]
+    fn truncated_archives_fail() {
+        let entries = [Entry {
+            name: "tex",
+            flags: 0x11,
+            data: texture_data([0x80, 0x80, 0x80, 0xFF]),
+            padding: vec![],
+        }];
+        let input = build_archive(true, &entries, &[0; 23], &[]);
+
+        for len in 0..input.len() {
+            assert!(
+                TextureArchive::from_bytes(&input[..len]).is_err(),
+                "{len} bytes were read successfully"
+            );
         }
     }
+
+    #[test]
+    fn random_buffers_never_panic() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..2000 {
+            let mut data: Vec<u8> = (0..rng.below(256)).map(|_| rng.next() as u8).collect();
+
+            // Keep the header plausible most of the time, so reading gets further in
+            if data.len() >= 4 && rng.below(4) != 0 {
+                data[..4].copy_from_slice(&[0, rng.below(4) as u8, 0, rng.below(2) as u8]);
+            }
+
+            let _ = TextureArchive::from_bytes(&data);
+        }
+    }
+
+    #[test]
+    fn mutated_archives_never_panic() {
+        let input = unusual_archive();
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+        for _ in 0..2000 {
+            let mut data = input.clone();
+            for _ in 0..=rng.below(4) {
+                let index = rng.below(data.len());
+                data[index] = rng.next() as u8;
+            }
+            data.truncate(data.len() - rng.below(64));
+
+            if let Ok(archive) = TextureArchive::from_bytes(&data) {
+                let _ = archive.to_bytes();
+            }
+            if let Ok((archive, _)) =
+                TextureArchive::from_bytes_with_mode(&data, TextureArchiveReadMode::Lenient)
+            {
+                let _ = archive.to_bytes();
+            }
+        }
+    }
+
+    /// Returns the unusual archive with the magic of its second texture broken, along with the
+    /// offset of that texture.
+    fn damaged_archive() -> (Vec<u8>, usize) {
+        let mut input = unusual_archive();
+        let offset = u32::from_be_bytes(input[8..12].try_into().unwrap()) as usize;
+        input[offset..offset + 4].copy_from_slice(b"XXXX");
+
+        (input, offset)
+    }
+
+    #[test]
+    fn strict_mode_fails_on_damaged_entry() {
+        let (input, offset) = damaged_archive();
+
+        let err = TextureArchive::from_bytes_with_mode(&input, TextureArchiveReadMode::Strict)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            &err,
+            TextureArchiveError::InvalidTexture { index: 1, name, offset: o, .. }
+                if name == "tex_b" && *o == offset as u64
+        ));
+        assert_eq!(TextureArchive::from_bytes(&input).err(), Some(err));
+    }
+
+    #[test]
+    fn lenient_mode_salvages_damaged_entry() {
+        let (input, offset) = damaged_archive();
+
+        let (archive, report) =
+            TextureArchive::from_bytes_with_mode(&input, TextureArchiveReadMode::Lenient).unwrap();
+        let diagnostics = report.diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0],
+            TextureArchiveError::InvalidTexture { index: 1, .. }
+        ));
+
+        // The names of the other textures still match them
+        let names: Vec<&str> = archive.textures().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["tex_a", "tex_c"]);
+        assert_eq!(archive.entries[1].flags, 0x11);
+
+        let damaged = &archive.damaged_entries[0];
+        assert_eq!((damaged.index, damaged.name.as_str()), (1, "tex_b"));
+        assert_eq!(damaged.archive_flags, 0x80);
+        assert_eq!(damaged.data, input[offset..offset + 0xA0]);
+
+        assert_eq!(archive.to_bytes(), input);
+    }
+
+    #[test]
+    fn lenient_mode_salvages_bad_offsets_and_names() {
+        let mut input = unusual_archive();
+        input[12..16].copy_from_slice(&[0xFF; 4]);
+        input[19] = 0x01; // first character of "tex_a"
+
+        assert!(matches!(
+            TextureArchive::from_bytes(&input),
+            Err(TextureArchiveError::OffsetOutOfBounds { index: 2, .. })
+        ));
+
+        let (archive, report) =
+            TextureArchive::from_bytes_with_mode(&input, TextureArchiveReadMode::Lenient).unwrap();
+        let diagnostics = report.diagnostics;
+        assert!(matches!(
+            diagnostics[..],
+            [
+                TextureArchiveError::OffsetOutOfBounds { index: 2, .. },
+                TextureArchiveError::InvalidName {
+                    index: 0,
+                    offset: 19
+                },
+            ]
+        ));
+        assert_eq!(archive.entries[0].texture.name, "?ex_a");
+        assert_eq!(archive.entries.len(), 2);
+        assert_eq!(archive.damaged_entries[0].index, 2);
+        assert_eq!(archive.damaged_entries[0].offset, 0xFFFF_FFFF);
+        assert!(archive.damaged_entries[0].data.is_empty());
+
+        // The orphaned data of the last texture is kept as trailing data, and the bad offset is
+        // written back unchanged. Only the replaced character of the name differs.
+        assert_eq!(&archive.trailing_data[..4], b"GCIX");
+        let mut expected = input.clone();
+        expected[19] = b'?';
+        assert_eq!(archive.to_bytes(), expected);
+    }
 }